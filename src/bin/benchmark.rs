@@ -0,0 +1,120 @@
+use std::env;
+use std::process;
+use std::time::{Duration, Instant};
+use raycasting::scene::samples;
+use raycasting::{Precision, Renderer, Scene, INCREMENTAL_TOLERANCE};
+
+const USAGE: &str = "\
+Usage: benchmark [OPTIONS]
+
+Renders a fixed set of scenes with incremental and per-pixel evaluation of the ray equation,
+prints the best time of each and checks that the two images agree.
+
+Options:
+    --width <PIXELS>             image width (default 1920)
+    --height <PIXELS>            image height (default 1080)
+    --threads <COUNT>            render threads, 0 for one per core (default 1)
+    --precision <TYPE>           single or double (default single)
+    --runs <COUNT>               renders per scene and mode, the fastest counts (default 5)
+    --help                       print this message";
+
+struct Options {
+    width: usize,
+    height: usize,
+    threads: usize,
+    precision: Precision,
+    runs: usize,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options { width: 1920, height: 1080, threads: 1, precision: Precision::Single, runs: 5 };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--width" => options.width = parse_number(flag, value)?,
+            "--height" => options.height = parse_number(flag, value)?,
+            "--threads" => options.threads = parse_number(flag, value)?,
+            "--precision" => options.precision = Precision::from_key(value).ok_or_else(|| format!("unknown precision {}", value))?,
+            "--runs" => options.runs = parse_number(flag, value)?,
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+
+    if options.width == 0 || options.height == 0 {
+        return Err("width and height must be positive".to_string());
+    }
+    options.runs = options.runs.max(1);
+    Ok(options)
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+fn best_time(renderer: &mut Renderer, scene: &Scene, options: &Options) -> (Duration, Vec<u8>) {
+    let best = (0..options.runs)
+        .map(|_| {
+            let start = Instant::now();
+            renderer.render(scene, options.width, options.height);
+            start.elapsed()
+        })
+        .min()
+        .unwrap();
+    (best, renderer.buffer().to_vec())
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = parse_options(&args).unwrap_or_else(|error| {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        process::exit(2);
+    });
+
+    let mut renderer = Renderer::new();
+    renderer.set_threads(options.threads);
+    renderer.set_precision(options.precision);
+
+    println!(
+        "{}x{}, {} thread(s), {} precision, best of {}",
+        options.width, options.height, renderer.threads(), options.precision.key(), options.runs
+    );
+    println!("{:<30} {:>12} {:>12} {:>8} {:>10}", "scene", "per pixel", "incremental", "speedup", "differing");
+
+    let mut within_tolerance = true;
+    for (name, scene) in samples() {
+        renderer.set_incremental(false);
+        let (per_pixel, reference) = best_time(&mut renderer, &scene, &options);
+        renderer.set_incremental(true);
+        let (incremental, image) = best_time(&mut renderer, &scene, &options);
+
+        // Pixels with a channel more than 1 away from the per-pixel image.
+        let differing = reference
+            .chunks(4)
+            .zip(image.chunks(4))
+            .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > 1))
+            .count();
+        let fraction = differing as f64 / (options.width * options.height) as f64;
+        within_tolerance &= fraction <= INCREMENTAL_TOLERANCE;
+
+        println!(
+            "{:<30} {:>9.1} ms {:>9.1} ms {:>7.2}x {:>9.4}%",
+            name,
+            per_pixel.as_secs_f64() * 1000.0,
+            incremental.as_secs_f64() * 1000.0,
+            per_pixel.as_secs_f64() / incremental.as_secs_f64(),
+            fraction * 100.0
+        );
+    }
+
+    if !within_tolerance {
+        eprintln!("error: incremental evaluation changed more than {}% of the pixels", INCREMENTAL_TOLERANCE * 100.0);
+        process::exit(1);
+    }
+}
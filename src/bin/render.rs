@@ -0,0 +1,182 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+use raycasting::image::{self, ImageFormat};
+use raycasting::scene::{euler_to_quaternion, PALETTE};
+use raycasting::{Camera, Fit, Object, Precision, Projection, Reflection, Renderer, Scene, SceneFile, Surface};
+
+const USAGE: &str = "\
+Usage: render [OPTIONS] --output <FILE>
+
+Renders a scene without opening a window and writes it to a PNG or PPM file.
+Object options apply to the current object; --object starts the next one.
+
+Object options:
+    --surface <KIND>             ellipsoid, hyperboloid-one-sheet, hyperboloid-two-sheets,
+                                 elliptic-paraboloid, hyperbolic-paraboloid, elliptic-cone
+                                 or elliptic-cylinder (default ellipsoid)
+    --a <VALUE>                  semi-axis along x (default 1)
+    --b <VALUE>                  semi-axis along y (default 1)
+    --c <VALUE>                  semi-axis along z (default 1)
+    --scale <X,Y,Z>              scale, no component may be zero (default 1,1,1)
+    --mirror                     allow negative scale components
+    --rotation <X,Y,Z>           rotation in radians, applied as Rx * Ry * Rz (default 0,0,0)
+    --translation <X,Y,Z>        translation (default 0,0,0)
+    --color <R,G,B>              colour with components in 0..1
+    --object                     start a new object
+
+Options:
+    --scene <FILE>               start from a scene file, later options override it
+    --perspective                use a perspective camera instead of an orthographic one
+    --fov <DEGREES>              field of view across the fitted side, perspective only (default 45)
+    --fit <SIDE>                 side spanning the view: shorter, width or height (default shorter)
+    --eye <X,Y,Z>                camera position (default 0,0,3)
+    --target <X,Y,Z>             point the camera looks at (default 0,0,0)
+    --reflection <MODEL>         phong or blinn-phong (default phong)
+    --ambient <VALUE>            ambient coefficient (default 0.1)
+    --diffuse <VALUE>            diffuse coefficient (default 0.7)
+    --specular <VALUE>           specular coefficient (default 0.3)
+    --shininess <VALUE>          specular exponent (default 20)
+    --light <X,Y,Z>              direction towards the light (default -1,1,1)
+    --background <R,G,B>         background colour with components in 0..1 (default 0,0,0)
+    --transparent                make the background transparent (PNG only)
+    --width <PIXELS>             image width (default 800)
+    --height <PIXELS>            image height (default 600)
+    --threads <COUNT>            render threads, 0 for one per core (default 0)
+    --precision <TYPE>           trace rays in single or double precision (default single)
+    --output <FILE>              output file, format chosen by the .png or .ppm extension
+    --help                       print this message";
+
+struct Options {
+    scene: Scene,
+    width: usize,
+    height: usize,
+    threads: usize,
+    precision: Precision,
+    output: PathBuf,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut scene = Scene::new();
+    let mut width = 800usize;
+    let mut height = 600usize;
+    let mut threads = 0usize;
+    let mut precision = Precision::Single;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        if flag == "--help" || flag == "-h" {
+            println!("{}", USAGE);
+            process::exit(0);
+        }
+        if flag == "--perspective" {
+            scene.camera.projection = Projection::Perspective;
+            continue;
+        }
+        if flag == "--transparent" {
+            scene.background.3 = 0.0;
+            continue;
+        }
+        if flag == "--mirror" {
+            scene.objects.last_mut().unwrap().mirror = true;
+            continue;
+        }
+        if flag == "--object" {
+            scene.objects.push(Object { color: PALETTE[scene.objects.len() % PALETTE.len()], ..Object::new() });
+            continue;
+        }
+
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        if flag == "--scene" {
+            let file = SceneFile::load(Path::new(value)).map_err(|error| format!("failed to load {}: {}", value, error))?;
+            scene = file.scene;
+            threads = file.threads;
+            precision = file.precision;
+            continue;
+        }
+
+        let object = scene.objects.last_mut().unwrap();
+        match flag.as_str() {
+            "--surface" => object.surface = Surface::from_key(value).ok_or_else(|| format!("unknown surface {}", value))?,
+            "--a" => object.a = parse_number(flag, value)?,
+            "--b" => object.b = parse_number(flag, value)?,
+            "--c" => object.c = parse_number(flag, value)?,
+            "--scale" => object.scale = parse_triple(flag, value)?,
+            "--rotation" => object.rotation = euler_to_quaternion(parse_triple(flag, value)?),
+            "--translation" => object.translation = parse_triple(flag, value)?,
+            "--color" => object.color = parse_triple(flag, value)?,
+            "--fov" => scene.camera.fov = parse_number(flag, value)?,
+            "--fit" => scene.camera.fit = Fit::from_key(value).ok_or_else(|| format!("unknown fit mode {}", value))?,
+            "--eye" => scene.camera.eye = parse_triple(flag, value)?,
+            "--target" => scene.camera.target = parse_triple(flag, value)?,
+            "--reflection" => scene.lighting.reflection = Reflection::from_key(value).ok_or_else(|| format!("unknown reflection model {}", value))?,
+            "--ambient" => scene.lighting.ambient = parse_number(flag, value)?,
+            "--diffuse" => scene.lighting.diffuse = parse_number(flag, value)?,
+            "--specular" => scene.lighting.specular = parse_number(flag, value)?,
+            "--shininess" => scene.lighting.shininess = parse_number(flag, value)?,
+            "--light" => scene.lighting.light_direction = parse_triple(flag, value)?,
+            "--background" => {
+                let (r, g, b) = parse_triple(flag, value)?;
+                scene.background = (r, g, b, scene.background.3);
+            }
+            "--width" => width = parse_number(flag, value)?,
+            "--height" => height = parse_number(flag, value)?,
+            "--threads" => threads = parse_number(flag, value)?,
+            "--precision" => precision = Precision::from_key(value).ok_or_else(|| format!("unknown precision {}", value))?,
+            "--output" | "-o" => output = Some(PathBuf::from(value)),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+
+    for (index, object) in scene.objects.iter().enumerate() {
+        object.validate().map_err(|error| format!("object {}: {}", index + 1, error))?;
+    }
+    if !Camera::fov_allowed(scene.camera.fov) {
+        return Err(format!("fov must be between 0 and 180 degrees, got {}", scene.camera.fov));
+    }
+    let output = output.ok_or_else(|| "missing --output".to_string())?;
+    if width == 0 || height == 0 {
+        return Err("width and height must be positive".to_string());
+    }
+
+    Ok(Options { scene, width, height, threads, precision, output })
+}
+
+fn parse_number<T: std::str::FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value.trim().parse().map_err(|_| format!("invalid value for {}: {}", flag, value))
+}
+
+fn parse_triple(flag: &str, value: &str) -> Result<(f64, f64, f64), String> {
+    let components = value
+        .split(',')
+        .map(|component| parse_number(flag, component))
+        .collect::<Result<Vec<f64>, String>>()?;
+    match components[..] {
+        [x, y, z] => Ok((x, y, z)),
+        _ => Err(format!("{} expects three comma-separated values, got {}", flag, value)),
+    }
+}
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let options = parse_options(&args).unwrap_or_else(|error| {
+        eprintln!("error: {}\n\n{}", error, USAGE);
+        process::exit(2);
+    });
+
+    let format = ImageFormat::from_path(&options.output).unwrap_or_else(|| {
+        eprintln!("error: output file must end in .png or .ppm");
+        process::exit(2);
+    });
+
+    let mut renderer = Renderer::new();
+    renderer.set_threads(options.threads);
+    renderer.set_precision(options.precision);
+    let buffer = renderer.render(&options.scene, options.width, options.height);
+
+    if let Err(error) = image::save(&options.output, format, buffer, options.width, options.height) {
+        eprintln!("error: failed to write {}: {}", options.output.display(), error);
+        process::exit(1);
+    }
+}
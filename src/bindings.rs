@@ -0,0 +1,252 @@
+//! Mouse bindings, read at startup from a TOML file. Every key is optional:
+//!
+//! ```toml
+//! [drag]                      # translate, rotate, scale or none
+//! left = "translate"
+//! right = "rotate"
+//! middle = "scale"
+//!
+//! [wheel]                     # scale, scale-z, translate-z, roll or none
+//! plain = "scale"
+//! ctrl = "roll"
+//! shift = "translate-z"
+//! middle = "scale-z"          # while the middle button is held
+//!
+//! [axes.x]                    # horizontal mouse movement
+//! sensitivity = 1.0           # multiplies the built-in speed
+//! invert = false
+//!
+//! [axes.y]                    # vertical mouse movement
+//! sensitivity = 1.0
+//! invert = false
+//!
+//! [axes.wheel]
+//! sensitivity = 1.0
+//! invert = false
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DragOperation {
+    Translate,
+    Rotate,
+    Scale,
+    None,
+}
+
+impl DragOperation {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "translate" => Some(DragOperation::Translate),
+            "rotate" => Some(DragOperation::Rotate),
+            "scale" => Some(DragOperation::Scale),
+            "none" => Some(DragOperation::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WheelOperation {
+    Scale,
+    ScaleZ,
+    TranslateZ,
+    Roll,
+    None,
+}
+
+impl WheelOperation {
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "scale" => Some(WheelOperation::Scale),
+            "scale-z" => Some(WheelOperation::ScaleZ),
+            "translate-z" => Some(WheelOperation::TranslateZ),
+            "roll" => Some(WheelOperation::Roll),
+            "none" => Some(WheelOperation::None),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Axis {
+    pub sensitivity: f64,
+    pub invert: bool,
+}
+
+impl Axis {
+    pub fn apply(&self, delta: f64) -> f64 {
+        if self.invert {
+            -delta * self.sensitivity
+        } else {
+            delta * self.sensitivity
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bindings {
+    pub left_drag: DragOperation,
+    pub right_drag: DragOperation,
+    pub middle_drag: DragOperation,
+
+    pub wheel: WheelOperation,
+    pub ctrl_wheel: WheelOperation,
+    pub shift_wheel: WheelOperation,
+    pub middle_wheel: WheelOperation,
+
+    pub x: Axis,
+    pub y: Axis,
+    pub wheel_axis: Axis,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings::new()
+    }
+}
+
+impl Bindings {
+    pub fn new() -> Self {
+        let axis = Axis { sensitivity: 1.0, invert: false };
+        Bindings {
+            left_drag: DragOperation::Translate,
+            right_drag: DragOperation::Rotate,
+            middle_drag: DragOperation::Scale,
+
+            wheel: WheelOperation::Scale,
+            ctrl_wheel: WheelOperation::Roll,
+            shift_wheel: WheelOperation::TranslateZ,
+            middle_wheel: WheelOperation::ScaleZ,
+
+            x: axis,
+            y: axis,
+            wheel_axis: axis,
+        }
+    }
+
+    // $XDG_CONFIG_HOME/raycasting/bindings.toml, falling back to %APPDATA% and ~/.config.
+    pub fn default_path() -> Option<PathBuf> {
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("raycasting").join("bindings.toml"))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Bindings::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let table = text.parse::<Table>().map_err(|error| error.to_string())?;
+        let mut bindings = Bindings::new();
+
+        if let Some(drag) = read_table(&table, "drag")? {
+            bindings.left_drag = read_operation(drag, "left", bindings.left_drag, DragOperation::from_key)?;
+            bindings.right_drag = read_operation(drag, "right", bindings.right_drag, DragOperation::from_key)?;
+            bindings.middle_drag = read_operation(drag, "middle", bindings.middle_drag, DragOperation::from_key)?;
+        }
+
+        if let Some(wheel) = read_table(&table, "wheel")? {
+            bindings.wheel = read_operation(wheel, "plain", bindings.wheel, WheelOperation::from_key)?;
+            bindings.ctrl_wheel = read_operation(wheel, "ctrl", bindings.ctrl_wheel, WheelOperation::from_key)?;
+            bindings.shift_wheel = read_operation(wheel, "shift", bindings.shift_wheel, WheelOperation::from_key)?;
+            bindings.middle_wheel = read_operation(wheel, "middle", bindings.middle_wheel, WheelOperation::from_key)?;
+        }
+
+        if let Some(axes) = read_table(&table, "axes")? {
+            bindings.x = read_axis(axes, "x", bindings.x)?;
+            bindings.y = read_axis(axes, "y", bindings.y)?;
+            bindings.wheel_axis = read_axis(axes, "wheel", bindings.wheel_axis)?;
+        }
+
+        Ok(bindings)
+    }
+}
+
+fn read_table<'a>(table: &'a Table, key: &str) -> Result<Option<&'a Table>, String> {
+    table
+        .get(key)
+        .map(|value| value.as_table().ok_or_else(|| format!("{} must be a table", key)))
+        .transpose()
+}
+
+fn read_operation<T>(table: &Table, key: &str, default: T, from_key: fn(&str) -> Option<T>) -> Result<T, String> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(value) => {
+            let name = value.as_str().ok_or_else(|| format!("{} must be a string", key))?;
+            from_key(name).ok_or_else(|| format!("unknown operation {} for {}", name, key))
+        }
+    }
+}
+
+fn read_axis(table: &Table, key: &str, default: Axis) -> Result<Axis, String> {
+    let Some(axis) = read_table(table, key)? else {
+        return Ok(default);
+    };
+
+    let sensitivity = match axis.get("sensitivity") {
+        None => default.sensitivity,
+        Some(value) => value
+            .as_float()
+            .or_else(|| value.as_integer().map(|value| value as f64))
+            .ok_or_else(|| format!("sensitivity of axis {} must be a number", key))?,
+    };
+    let invert = match axis.get("invert") {
+        None => default.invert,
+        Some(Value::Boolean(invert)) => *invert,
+        Some(_) => return Err(format!("invert of axis {} must be true or false", key)),
+    };
+
+    Ok(Axis { sensitivity, invert })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_file_keeps_defaults() {
+        assert_eq!(Bindings::parse(""), Ok(Bindings::new()));
+    }
+
+    #[test]
+    fn keys_override_defaults() {
+        let bindings = Bindings::parse(
+            "[drag]\nleft = \"rotate\"\nmiddle = \"none\"\n\
+             [wheel]\nctrl = \"scale-z\"\n\
+             [axes.y]\ninvert = true\n\
+             [axes.wheel]\nsensitivity = 2\n",
+        )
+        .unwrap();
+
+        let expected = Bindings {
+            left_drag: DragOperation::Rotate,
+            middle_drag: DragOperation::None,
+            ctrl_wheel: WheelOperation::ScaleZ,
+            y: Axis { sensitivity: 1.0, invert: true },
+            wheel_axis: Axis { sensitivity: 2.0, invert: false },
+            ..Bindings::new()
+        };
+        assert_eq!(bindings, expected);
+        assert_eq!(bindings.y.apply(0.5), -0.5);
+        assert_eq!(bindings.wheel_axis.apply(0.5), 1.0);
+    }
+
+    #[test]
+    fn invalid_values_are_refused() {
+        assert_eq!(Bindings::parse("[drag]\nleft = \"spin\""), Err("unknown operation spin for left".to_string()));
+        assert_eq!(Bindings::parse("drag = 1"), Err("drag must be a table".to_string()));
+        assert_eq!(Bindings::parse("[wheel]\nplain = 1"), Err("plain must be a string".to_string()));
+        assert_eq!(Bindings::parse("[axes.x]\ninvert = 1"), Err("invert of axis x must be true or false".to_string()));
+        assert_eq!(Bindings::parse("[axes.x]\nsensitivity = \"fast\""), Err("sensitivity of axis x must be a number".to_string()));
+        assert!(Bindings::parse("[drag").is_err());
+    }
+}
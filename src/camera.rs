@@ -0,0 +1,186 @@
+use nalgebra::{convert, RealField, Vector3, Vector4};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Projection {
+    Orthographic,
+    Perspective,
+}
+
+// Which side of the image spans the screen range -1..1. Pixels stay square, so the other side
+// shows more or less of the scene depending on the aspect ratio.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Fit {
+    Width,
+    Height,
+    Shorter,
+}
+
+impl Fit {
+    pub const ALL: [Fit; 3] = [Fit::Shorter, Fit::Width, Fit::Height];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fit::Width => "Fit width",
+            Fit::Height => "Fit height",
+            Fit::Shorter => "Fit shorter side",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Fit::Width => "width",
+            Fit::Height => "height",
+            Fit::Shorter => "shorter",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Fit::ALL.into_iter().find(|fit| fit.key() == key)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Camera {
+    pub projection: Projection,
+    pub eye: (f64, f64, f64),
+    pub target: (f64, f64, f64),
+    // Field of view in degrees across the fitted side, used by the perspective projection.
+    pub fov: f64,
+    pub fit: Fit,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new()
+    }
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Camera {
+            projection: Projection::Orthographic,
+            eye: (0.0, 0.0, 3.0),
+            target: (0.0, 0.0, 0.0),
+            fov: 45.0,
+            fit: Fit::Shorter,
+        }
+    }
+
+    // A perspective view needs a field of view strictly between nothing and a half-turn.
+    pub fn fov_allowed(fov: f64) -> bool {
+        fov > 0.0 && fov < 180.0
+    }
+
+    // Pixels per screen unit for an image of the given size. Halving with integer division
+    // keeps the centre pixel at exactly 0.
+    pub fn pixel_scale(&self, width: usize, height: usize) -> f64 {
+        let side = match self.fit {
+            Fit::Width => width,
+            Fit::Height => height,
+            Fit::Shorter => width.min(height),
+        };
+        (side / 2).max(1) as f64
+    }
+
+    // Unit forward, right and up vectors of the view.
+    pub fn basis(&self) -> (Vector3<f64>, Vector3<f64>, Vector3<f64>) {
+        let eye = Vector3::new(self.eye.0, self.eye.1, self.eye.2);
+        let target = Vector3::new(self.target.0, self.target.1, self.target.2);
+        let forward = (target - eye).try_normalize(f64::EPSILON).unwrap_or(-Vector3::z());
+
+        // Keep world y pointing up on screen unless the camera looks straight along it.
+        let right = forward
+            .cross(&Vector3::y())
+            .try_normalize(f64::EPSILON)
+            .unwrap_or_else(|| forward.cross(&Vector3::z()).normalize());
+        let up = right.cross(&forward);
+
+        (forward, right, up)
+    }
+
+    pub fn rays<T: RealField + Copy>(&self) -> Rays<T> {
+        let eye = Vector3::new(self.eye.0, self.eye.1, self.eye.2).map(convert);
+        let (forward, right, up) = self.basis();
+        let (forward, right, up) = (forward.map(convert), right.map(convert), up.map(convert));
+
+        let (right, up) = match self.projection {
+            Projection::Orthographic => (right, up),
+            Projection::Perspective => {
+                let tan = convert::<f64, T>((self.fov.to_radians() / 2.0).tan());
+                (right * tan, up * tan)
+            }
+        };
+
+        Rays { projection: self.projection, eye, forward, right, up }
+    }
+}
+
+// Camera basis precomputed once per pass. Screen coordinates run from -1 to 1 across the fitted side.
+pub struct Rays<T: RealField + Copy> {
+    pub projection: Projection,
+    eye: Vector3<T>,
+    forward: Vector3<T>,
+    right: Vector3<T>,
+    up: Vector3<T>,
+}
+
+impl<T: RealField + Copy> Rays<T> {
+    // Homogeneous origin and direction of the ray through the screen point (x, y).
+    pub fn ray(&self, x: T, y: T) -> (Vector4<T>, Vector4<T>) {
+        let offset = self.right * x + self.up * y;
+        let (origin, direction) = match self.projection {
+            Projection::Orthographic => (self.eye + offset, self.forward),
+            Projection::Perspective => (self.eye, self.forward + offset),
+        };
+        (origin.push(T::one()), direction.push(T::zero()))
+    }
+
+    // How origin and direction change per unit of x, so that ray(x, y) is ray(0, y) plus x times this.
+    pub fn step(&self) -> (Vector4<T>, Vector4<T>) {
+        match self.projection {
+            Projection::Orthographic => (self.right.push(T::zero()), Vector4::zeros()),
+            Projection::Perspective => (Vector4::zeros(), self.right.push(T::zero())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::Renderer;
+    use crate::scene::Scene;
+
+    // Columns and rows covered by something other than the transparent background.
+    fn drawn_size(rgba: &[u8], width: usize) -> (usize, usize) {
+        let drawn = |index: usize| rgba[index * 4 + 3] > 0;
+        let height = rgba.len() / 4 / width;
+        let columns = (0..width).filter(|&x| (0..height).any(|y| drawn(y * width + x))).count();
+        let rows = (0..height).filter(|&y| (0..width).any(|x| drawn(y * width + x))).count();
+        (columns, rows)
+    }
+
+    // Pixels stay square whichever side is fitted, so a sphere is as wide as it is tall.
+    #[test]
+    fn spheres_stay_round_on_any_image() {
+        let mut scene = Scene::new();
+        scene.background = (0.0, 0.0, 0.0, 0.0);
+        scene.objects[0].scale = (0.4, 0.4, 0.4);
+
+        let mut renderer = Renderer::new();
+        for (width, height) in [(200, 100), (100, 200)] {
+            for projection in [Projection::Orthographic, Projection::Perspective] {
+                for fit in Fit::ALL {
+                    scene.camera = Camera { projection, fit, ..Camera::new() };
+                    let (columns, rows) = drawn_size(renderer.render(&scene, width, height), width);
+                    assert_eq!(columns, rows, "{:?} {:?} at {}x{}", projection, fit, width, height);
+
+                    // A radius of 0.4 screen units, where the fitted side spans two.
+                    if projection == Projection::Orthographic {
+                        let diameter = 0.8 * scene.camera.pixel_scale(width, height);
+                        assert!((columns as f64 - diameter).abs() <= 1.0, "{:?}: {} pixels across", fit, columns);
+                    }
+                }
+            }
+        }
+    }
+}
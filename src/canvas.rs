@@ -1,220 +1,279 @@
-use std::time::Duration;
-use druid::{BoxConstraints, Color, Env, Event, EventCtx, ImageBuf, KbKey, LayoutCtx, LifeCycle, LifeCycleCtx, MouseButton, PaintCtx, RenderContext, Size, UpdateCtx, Widget};
-use druid::piet::ImageFormat;
-use nalgebra::{Matrix4, Vector3, Vector4};
+use std::mem;
+use std::time::{Duration, Instant};
+use druid::{BoxConstraints, Code, Color, Env, Event, EventCtx, ImageBuf, KbKey, KeyEvent, LayoutCtx, LifeCycle, LifeCycleCtx, MouseButton, PaintCtx, Point, Rect, RenderContext, Size, TimerToken, UpdateCtx, Widget};
+use druid::piet::{ImageFormat, InterpolationMode, PietImage};
+use nalgebra::{Unit, UnitQuaternion, Vector3};
+use raycasting::Camera;
+use crate::bindings::{Bindings, DragOperation, WheelOperation};
+use crate::worker::{Frame, RenderWorker, FRAME_READY};
 use crate::AppState;
 
 pub struct Canvas {
-    canvas: Vec<u8>,
-    pixels_computed: Vec<bool>,
-    a: f64,
-    b: f64,
-    c: f64,
-    m: f64,
-    scale: (f64, f64, f64),
-    rotation: (f64, f64, f64),
-    translation: (f64, f64, f64),
+    bindings: Bindings,
+    worker: Option<RenderWorker>,
+    frame: Option<Frame>,
+    image: Option<PietImage>,
+    stats: Option<PaintStats>,
+    generation: u64,
+    // Whether the frame for `generation` is still being rendered.
+    in_flight: bool,
     width: usize,
     height: usize,
-    
-    max_timer: f64,
-    current_timer: f64,
-    timer_step: f64,
 
-    previous_accuracy: usize,
+    settings: Refinement,
+    timer: Option<TimerToken>,
+    current_timer: f64,
     accuracy: usize,
+    // Seconds per traced pixel, measured from finished passes.
+    pixel_cost: Option<f64>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Refinement {
+    max_timer: f64,
+    timer_step: f64,
     min_accuracy: usize,
+    full_accuracy: bool,
+    adaptive: bool,
+    // Seconds a frame may take while the scene is being changed.
+    target_frame_time: f64,
+}
+
+impl Refinement {
+    fn new(data: &AppState) -> Self {
+        Refinement {
+            max_timer: data.max_timer.max(0.0),
+            timer_step: data.timer_step.max(0.01),
+            min_accuracy: data.min_accuracy.max(1),
+            full_accuracy: data.full_accuracy,
+            adaptive: data.adaptive_refinement,
+            target_frame_time: data.target_frame_time.max(1.0) / 1000.0,
+        }
+    }
 }
 
 impl Canvas {
-    pub fn new() -> Self {
+    // With `benchmark`, every uploaded frame prints how much time the image cache has saved.
+    pub fn new(bindings: Bindings, benchmark: bool) -> Self {
         Canvas {
-            canvas: Vec::new(),
-            pixels_computed: Vec::new(),
-            a: 1.0,
-            b: 1.0,
-            c: 1.0,
-            m: 1.0,
-            rotation: (0.0, 0.0, 0.0),
-            translation: (0.0, 0.0, 0.0),
-            scale: (1.0, 1.0, 1.0),
+            bindings,
+            worker: None,
+            frame: None,
+            image: None,
+            stats: benchmark.then(PaintStats::default),
+            generation: 0,
+            in_flight: false,
             width: 0,
             height: 0,
 
-            max_timer: 0.2,
+            settings: Refinement {
+                max_timer: 0.2,
+                timer_step: 0.1,
+                min_accuracy: 32,
+                full_accuracy: false,
+                adaptive: true,
+                target_frame_time: 1.0 / 30.0,
+            },
+            timer: None,
             current_timer: 0.0,
-            timer_step: 0.1,
-
-            previous_accuracy: 1,
             accuracy: 1,
-            min_accuracy: 32,
+            pixel_cost: None,
         }
     }
 
-    fn draw(&mut self, a: f64, b: f64, c: f64, m: f64, scale: (f64, f64, f64), rotation: (f64, f64, f64), translation: (f64, f64, f64), width: usize, height: usize) {
-        if !self.update(a, b, c, m, scale, rotation, translation, width, height) {
-            if self.previous_accuracy != self.accuracy {
-                self.previous_accuracy = self.accuracy;
-            } else {
-                return;
-            }
-        } else { 
-            self.reset_accuracy();
-            self.pixels_computed.fill(false);
-        } 
-
-        self.canvas.resize(width * height * 4, 0);
-        self.pixels_computed.resize(width * height, false);
-        
-        let m = m as i32;
-        
-        let d = self.get_d();
-
-        for i in 0..width {
-            for j in 0..height {
-                let pixel_index = j * width + i;
-                if self.pixels_computed[pixel_index] {
-                    continue;
-                }
-                if i % self.accuracy != 0 || j % self.accuracy != 0 {
-                    let colored_index = (j / self.accuracy * self.accuracy * width + i / self.accuracy * self.accuracy) * 4;
-                    let index = (j * width + i) * 4;
-                    (self.canvas[index], self.canvas[index + 1], self.canvas[index + 2], self.canvas[index + 3]) = 
-                        (self.canvas[colored_index], self.canvas[colored_index + 1], self.canvas[colored_index + 2], self.canvas[colored_index + 3]);
-                    continue;
-                } 
-                
-                let x = (i as i32 - (width as i32 / 2)) as f32 / ((width / 2) as f32);
-                let y = (j as i32 - (height as i32 / 2)) as f32 / ((height / 2) as f32) * (-1.0);
-                let index = (j * width + i) * 4;
-
-                let a = d.m33;
-                let b = d.m13*x + d.m23*y + d.m31*x + d.m32*y + d.m34;
-                let b2 = b * b;
-                
-                let delta =
-                    b2
-                    - 4.0
-                    * (
-                        d.m11*x*x + d.m12*x*y + d.m14*x
-                        + d.m21*x*y + d.m22*y*y + d.m24*y
-                        + d.m41*x + d.m42*y + d.m44
-                    )
-                    * a;
-                
-                
-                if delta >= 0.0 {
-                    let z = if a > 0.0  
-                        { (-b + delta.sqrt()) / (2.0 * a) } 
-                        else 
-                        { (-b - delta.sqrt()) / (2.0 * a) };
-
-                    let n = Vector3
-                        ::new(
-                            2.0*d.m11*x + d.m12*y + d.m13*z + d.m14 + d.m21*y + d.m31*z + d.m41,
-                            d.m12*x + d.m21*x + 2.0*d.m22*y + d.m23*z + d.m24 + d.m32*z + d.m42,
-                            d.m13*x + d.m23*y + d.m31*x + d.m32*y + 2.0*d.m33*z + d.m34 + d.m43
-                        )
-                        .normalize();
-                    let v = Vector3::new(-x, -y, 100.0 - z).normalize();
-
-                    let intensity = n.dot(&v).clamp(0.0, 1.0).powi(m) as f64;
-
-                    let yellow = Color::YELLOW.as_rgba();
-                    let color = Color::rgb(yellow.0 * intensity, yellow.1 * intensity, yellow.2 * intensity);
-
-                    (self.canvas[index], self.canvas[index + 1], self.canvas[index + 2], self.canvas[index + 3]) = color.as_rgba8();
-                    self.pixels_computed[pixel_index] = true;
-                } else {
-                    (self.canvas[index], self.canvas[index + 1], self.canvas[index + 2], self.canvas[index + 3]) = (0, 0, 0, 255);
-                    self.pixels_computed[pixel_index] = true;
-                }
-            }
+    fn request_frame(&mut self, data: &AppState) {
+        if let Some(worker) = &self.worker {
+            self.generation = worker.request(data.scene(), self.width, self.height, self.accuracy, data.threads as usize, data.precision);
+            self.in_flight = true;
         }
     }
 
-    fn update(&mut self, a: f64, b: f64, c: f64, m: f64, scale: (f64, f64, f64), rotation: (f64, f64, f64), translation: (f64, f64, f64), width: usize, height: usize) -> bool {
-        let result = self.a != a 
-            || self.b != b 
-            || self.c != c 
-            || self.m != m 
-            || self.scale != scale 
-            || self.rotation != rotation 
-            || self.translation != translation
-            || self.width != width 
-            || self.height != height;
-
-        self.a = a;
-        self.b = b;
-        self.c = c;
-        self.m = m;
-        self.scale = scale;
-        self.rotation = rotation;
-        self.translation = translation;
-        self.width = width;
-        self.height = height;
-
-        result
-    }
-    
     fn increase_accuracy(&mut self) -> bool {
         if self.accuracy > 1 {
             self.accuracy /= 2;
-            return true;
-        } else { 
-            return false;
-        } 
+            true
+        } else {
+            false
+        }
     }
-    
+
     fn reset_accuracy(&mut self) {
-        self.accuracy = self.min_accuracy;
-    }
-    
-    fn get_d(&self) -> Matrix4<f32> {
-        let d = Matrix4::from_diagonal(&Vector4::new((1.0 / self.a / self.a) as f32, (1.0 / self.b / self.b) as f32, (1.0 / self.c / self.c) as f32, -1.0));
-        let m = self.get_translation_matrix() 
-            * self.get_rotation_matrix() 
-            * Matrix4::from_diagonal(&Vector4::new(self.scale.0 as f32, self.scale.1 as f32, self.scale.2 as f32, 1.0));
-        let mi = m.try_inverse().unwrap_or_else(|| Matrix4::identity());
-        return mi.transpose() * d * mi;
+        self.accuracy = if self.settings.full_accuracy {
+            1
+        } else if self.settings.adaptive {
+            self.interactive_accuracy()
+        } else {
+            self.settings.min_accuracy
+        };
     }
-    
-    fn get_rotation_matrix(&self) -> Matrix4<f32> {
-        let x = self.rotation.0 as f32;
-        let y = self.rotation.1 as f32;
-        let z = self.rotation.2 as f32;
-        let rx = Matrix4::new(
-            1.0, 0.0, 0.0, 0.0,
-            0.0, x.cos(), -x.sin(), 0.0,
-            0.0, x.sin(), x.cos(), 0.0,
-            0.0, 0.0, 0.0, 1.0
-        );
-        let ry = Matrix4::new(
-            y.cos(), 0.0, y.sin(), 0.0,
-            0.0, 1.0, 0.0, 0.0,
-            -y.sin(), 0.0, y.cos(), 0.0,
-            0.0, 0.0, 0.0, 1.0
-        );
-        let rz = Matrix4::new(
-            z.cos(), -z.sin(), 0.0, 0.0,
-            z.sin(), z.cos(), 0.0, 0.0,
-            0.0, 0.0, 1.0, 0.0,
-            0.0, 0.0, 0.0, 1.0
-        );
-        return rx * ry * rz;
+
+    // The finest step of the usual halving schedule that is expected to render within the
+    // target frame time. Without a measurement yet, start from the coarsest.
+    fn interactive_accuracy(&self) -> usize {
+        let Some(pixel_cost) = self.pixel_cost else {
+            return self.settings.min_accuracy;
+        };
+
+        let mut accuracy = self.settings.min_accuracy;
+        while accuracy > 1 {
+            let finer = accuracy / 2;
+            let pixels = self.width.div_ceil(finer) * self.height.div_ceil(finer);
+            if pixels as f64 * pixel_cost > self.settings.target_frame_time {
+                break;
+            }
+            accuracy = finer;
+        }
+        accuracy
     }
-    
-    fn get_translation_matrix(&self) -> Matrix4<f32> {
-        Matrix4::new(
-            1.0, 0.0, 0.0, self.translation.0 as f32,
-            0.0, 1.0, 0.0, self.translation.1 as f32,
-            0.0, 0.0, 1.0, self.translation.2 as f32,
-            0.0, 0.0, 0.0, 1.0
-        )
+
+    fn measure(&mut self, frame: &Frame) {
+        // Tiny passes are dominated by overhead and say little about the cost of a pixel.
+        if frame.pixels_shaded < 1000 {
+            return;
+        }
+        let cost = frame.elapsed.as_secs_f64() / frame.pixels_shaded as f64;
+        self.pixel_cost = Some(self.pixel_cost.map_or(cost, |previous| (previous + cost) / 2.0));
     }
-    
+
     fn reset_timer(&mut self) {
-        self.current_timer = self.max_timer;
+        self.current_timer = self.settings.max_timer;
+    }
+
+    fn idle(&self) -> bool {
+        self.current_timer <= 0.0
+    }
+
+    // The timer only runs while it has something to do: counting down to the next step of the
+    // fixed schedule, or to the moment the adaptive one may refine. Once the image is final, or
+    // the adaptive schedule is driven by arriving frames, nothing wakes the UI thread.
+    fn arm_timer(&mut self, request_timer: impl FnOnce(Duration) -> TimerToken) {
+        let waiting = self.accuracy > 1 && !(self.settings.adaptive && self.idle());
+        if waiting && self.timer.is_none() {
+            self.timer = Some(request_timer(Duration::from_secs_f64(self.settings.timer_step)));
+        }
+    }
+
+    // Returns whether any of the refinement settings changed.
+    fn apply_settings(&mut self, data: &AppState) -> bool {
+        let settings = Refinement::new(data);
+        if settings == self.settings {
+            return false;
+        }
+        self.settings = settings;
+        true
+    }
+
+    // Pointer positions are in pixels, y pointing down.
+    fn drag(&mut self, operation: DragOperation, from: (f64, f64), to: (f64, f64), data: &mut AppState) {
+        let dx = self.bindings.x.apply(to.0 - from.0);
+        let dy = self.bindings.y.apply(to.1 - from.1);
+        match operation {
+            DragOperation::Translate => {
+                let pixel_scale = data.camera().pixel_scale(self.width, self.height);
+                let object = data.selected_object_mut();
+                object.translation.0 += dx / pixel_scale;
+                object.translation.1 -= dy / pixel_scale;
+            }
+            DragOperation::Rotate => {
+                let rotation = self.arcball(&data.camera(), from, (from.0 + dx, from.1 + dy));
+                data.selected_object_mut().rotate(rotation);
+            }
+            DragOperation::Scale => {
+                let object = data.selected_object_mut();
+                let (x, y, z) = object.scale;
+                object.set_scale((x + dx / 1000.0, y - dy / 1000.0, z));
+            }
+            DragOperation::None => return,
+        }
+
+        self.reset_accuracy();
+        self.reset_timer();
+    }
+
+    fn wheel(&mut self, operation: WheelOperation, amount: f64, data: &mut AppState) {
+        let (forward, _, _) = data.camera().basis();
+        let object = data.selected_object_mut();
+        match operation {
+            WheelOperation::Scale => {
+                let (x, y, z) = object.scale;
+                object.set_scale((x + amount, y + amount, z + amount));
+            }
+            WheelOperation::ScaleZ => {
+                let (x, y, z) = object.scale;
+                object.set_scale((x, y, z + amount));
+            }
+            WheelOperation::TranslateZ => object.translation.2 += amount,
+            WheelOperation::Roll => object.rotate(UnitQuaternion::from_axis_angle(&Unit::new_normalize(-forward), amount)),
+            WheelOperation::None => return,
+        }
+
+        self.reset_accuracy();
+        self.reset_timer();
+    }
+
+    fn nudge(&mut self, nudge: Nudge, step: f64, data: &mut AppState) {
+        let (forward, right, up) = data.camera().basis();
+        let object = data.selected_object_mut();
+        match nudge {
+            // Along the screen, wherever the camera looks from.
+            Nudge::Translate(x, y) => {
+                let offset = (right * x + up * y) * step * TRANSLATION_STEP;
+                object.translation.0 += offset.x;
+                object.translation.1 += offset.y;
+                object.translation.2 += offset.z;
+            }
+            Nudge::Rotate(axis, direction) => {
+                let axis = match axis {
+                    ScreenAxis::Right => right,
+                    ScreenAxis::Up => up,
+                    ScreenAxis::View => -forward,
+                };
+                let angle = direction * step * ROTATION_STEP.to_radians();
+                object.rotate(UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle));
+            }
+            Nudge::Scale(direction) => {
+                let (x, y, z) = object.scale;
+                let amount = direction * step * SCALE_STEP;
+                object.set_scale((x + amount, y + amount, z + amount));
+            }
+            Nudge::Reset => {
+                object.scale = (1.0, 1.0, 1.0);
+                object.rotation = UnitQuaternion::identity();
+                object.translation = (0.0, 0.0, 0.0);
+                // The camera goes back to where it starts, keeping its projection and fit.
+                let camera = Camera::new();
+                (data.eye, data.target, data.fov) = (camera.eye, camera.target, camera.fov);
+            }
+        }
+
+        self.reset_accuracy();
+        self.reset_timer();
+    }
+
+    // Shoemake's arcball: a unit sphere filling the shorter side of the view is dragged from one
+    // point to the other, and the rotation between them is carried over to world space.
+    fn arcball(&self, camera: &Camera, from: (f64, f64), to: (f64, f64)) -> UnitQuaternion<f64> {
+        let (from, to) = (self.arcball_point(from), self.arcball_point(to));
+        let Some((axis, angle)) = UnitQuaternion::rotation_between(&from, &to).and_then(|rotation| rotation.axis_angle()) else {
+            return UnitQuaternion::identity();
+        };
+
+        let (forward, right, up) = camera.basis();
+        let axis = right * axis.x + up * axis.y - forward * axis.z;
+        UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle)
+    }
+
+    // Screen x to the right, y up and z towards the viewer. Points outside the ball land on its rim.
+    fn arcball_point(&self, (x, y): (f64, f64)) -> Vector3<f64> {
+        let radius = (self.width.min(self.height) as f64 / 2.0).max(1.0);
+        let x = (x - self.width as f64 / 2.0) / radius;
+        let y = (self.height as f64 / 2.0 - y) / radius;
+        let length = x * x + y * y;
+        if length <= 1.0 {
+            Vector3::new(x, y, (1.0 - length).sqrt())
+        } else {
+            Vector3::new(x, y, 0.0) / length.sqrt()
+        }
     }
 }
 
@@ -227,6 +286,10 @@ impl Widget<AppState> for Canvas {
                     KbKey::Shift => data.shift_clicked = true,
                     _ => {},
                 }
+                if let Some(nudge) = key_nudge(k) {
+                    self.nudge(nudge, nudge_step(k), data);
+                    ctx.set_handled();
+                }
             }
             Event::KeyUp(k) => {
                 match k.key {
@@ -236,23 +299,21 @@ impl Widget<AppState> for Canvas {
                 }
             }
             Event::Wheel(m) => {
-                if data.ctrl_clicked  {
-                    data.rotation.2 += m.wheel_delta.y / -1000.0;
-                    data.normalize_rotation();
+                // Most platforms turn the wheel sideways while Shift is held.
+                let (operation, delta) = if data.ctrl_clicked {
+                    (self.bindings.ctrl_wheel, m.wheel_delta.y)
                 } else if data.shift_clicked {
-                    data.translation.2 += m.wheel_delta.x / -1000.0;
-                } else if data.scroll_clicked { 
-                    data.scale.2 += m.wheel_delta.y / -1000.0;
+                    (self.bindings.shift_wheel, m.wheel_delta.x)
+                } else if data.scroll_clicked {
+                    (self.bindings.middle_wheel, m.wheel_delta.y)
                 } else {
-                    data.scale.0 += m.wheel_delta.y / -1000.0;
-                    data.scale.1 += m.wheel_delta.y / -1000.0;
-                    data.scale.2 += m.wheel_delta.y / -1000.0;
-                }
-                
-                self.reset_accuracy();
-                self.reset_timer();
+                    (self.bindings.wheel, m.wheel_delta.y)
+                };
+                self.wheel(operation, self.bindings.wheel_axis.apply(delta / -1000.0), data);
             }
             Event::MouseDown(m) => {
+                // Commits any transform field being typed into, so it follows the drag again.
+                ctx.request_focus();
                 match m.button {
                     MouseButton::Right => {
                         data.right_button_clicked = true;
@@ -279,80 +340,242 @@ impl Widget<AppState> for Canvas {
             }
             Event::MouseMove(m) => {
                 if m.buttons.contains(MouseButton::Right) {
-                    data.rotation.0 += (m.pos.y - data.right_button_position.1) / 100.0;
-                    data.rotation.1 += (m.pos.x - data.right_button_position.0) / 100.0;
+                    let position = data.right_button_position;
                     data.right_button_position = (m.pos.x, m.pos.y);
-                    
-                    data.normalize_rotation();
-                    self.reset_accuracy();
-                    self.reset_timer();
+                    self.drag(self.bindings.right_drag, position, (m.pos.x, m.pos.y), data);
                 }
                 if m.buttons.contains(MouseButton::Left) {
-                    data.translation.0 += (m.pos.x - data.left_button_position.0) / self.width as f64 * 2.0;
-                    data.translation.1 += (data.left_button_position.1 - m.pos.y) / self.height as f64 * 2.0;
+                    let position = data.left_button_position;
                     data.left_button_position = (m.pos.x, m.pos.y);
-                    
-                    self.reset_accuracy();
-                    self.reset_timer();
+                    self.drag(self.bindings.left_drag, position, (m.pos.x, m.pos.y), data);
                 }
                 if m.buttons.contains(MouseButton::Middle) {
-                    data.scale.0 += (m.pos.x - data.scroll_position.0) / 1000.0;
-                    data.scale.1 += (data.scroll_position.1 - m.pos.y) / 1000.0;
+                    let position = data.scroll_position;
                     data.scroll_position = (m.pos.x, m.pos.y);
-                    
-                    self.reset_accuracy();
-                    self.reset_timer();
+                    self.drag(self.bindings.middle_drag, position, (m.pos.x, m.pos.y), data);
                 }
             }
             Event::WindowConnected => {
-                self.reset_timer();
-                ctx.request_timer(Duration::from_secs_f64(self.timer_step));
                 ctx.request_focus();
             }
-            Event::Timer(_) => {
-                self.current_timer -= self.timer_step;
-                if self.current_timer <= 0.0 { 
+            Event::Timer(token) if self.timer == Some(*token) => {
+                self.timer = None;
+                let was_idle = self.idle();
+                self.current_timer -= self.settings.timer_step;
+                if self.settings.adaptive {
+                    // Once idle, each refinement step is requested as soon as the previous one arrives.
+                    if !was_idle && self.idle() && !self.in_flight && self.increase_accuracy() {
+                        self.request_frame(data);
+                    }
+                } else if self.idle() {
                     self.reset_timer();
-                    self.increase_accuracy();
-                } 
-                ctx.request_timer(Duration::from_secs_f64(self.timer_step));
+                    if self.increase_accuracy() {
+                        self.request_frame(data);
+                    }
+                }
+                self.arm_timer(|deadline| ctx.request_timer(deadline));
+            }
+            Event::Command(command) if command.is(FRAME_READY) => {
+                if let Some(frame) = command.get_unchecked(FRAME_READY).take() {
+                    if frame.generation == self.generation {
+                        self.in_flight = false;
+                        self.measure(&frame);
+                        data.accuracy = frame.accuracy;
+                        if frame.changed {
+                            self.frame = Some(frame);
+                            self.image = None;
+                            ctx.request_paint();
+                        }
+
+                        if self.settings.adaptive && self.idle() && self.increase_accuracy() {
+                            self.request_frame(data);
+                        }
+                    }
+                }
+                ctx.set_handled();
             }
             _ => {}
         }
-        data.accuracy = self.accuracy;
     }
 
-    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &AppState, _env: &Env) { }
+    fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, _env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.apply_settings(data);
+            self.worker = Some(RenderWorker::spawn(ctx.get_external_handle(), ctx.widget_id()));
+        }
+    }
 
-    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &AppState, _data: &AppState, _env: &Env) {
-        ctx.request_paint();
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &AppState, data: &AppState, _env: &Env) {
+        if self.apply_settings(data) || old_data.precision != data.precision || old_data.scene() != data.scene() {
+            self.reset_accuracy();
+            self.reset_timer();
+            self.request_frame(data);
+            self.arm_timer(|deadline| ctx.request_timer(deadline));
+        }
     }
 
-    fn layout(&mut self, _layout_ctx: &mut LayoutCtx, bc: &BoxConstraints, _data: &AppState, _env: &Env) -> Size {
-        bc.max()
+    fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &AppState, _env: &Env) -> Size {
+        let size = bc.max();
+        if (size.width as usize, size.height as usize) != (self.width, self.height) {
+            self.width = size.width as usize;
+            self.height = size.height as usize;
+            self.reset_accuracy();
+            self.reset_timer();
+            self.request_frame(data);
+            self.arm_timer(|deadline| ctx.request_timer(deadline));
+        }
+        size
     }
 
-    fn paint(&mut self, ctx: &mut PaintCtx, data: &AppState, _env: &Env) {
-        let start = chrono::Local::now();
-        
+    fn paint(&mut self, ctx: &mut PaintCtx, _data: &AppState, _env: &Env) {
+        let start = Instant::now();
         let rect = ctx.size().to_rect();
-        let width = rect.width() as usize;
-        let height = rect.height() as usize;
-
-        self.draw(data.a, data.b, data.c, data.m, data.scale, data.rotation, data.translation, width, height);
-
-        let image = ImageBuf
-            ::from_raw(
-                self.canvas.clone(),
-                ImageFormat::RgbaSeparate,
-                width,
-                height
-            )
-            .to_image(ctx.render_ctx);
-        ctx.draw_image(&image, rect, druid::piet::InterpolationMode::Bilinear);
-
-        let end = chrono::Local::now();
-        let duration = end - start;
-        println!("FPS: {:?}", 1.0 / duration.num_milliseconds() as f64 * 1000.0);
+        paint_checkerboard(ctx);
+
+        // The image is uploaded once per frame, later paints of the same frame reuse it.
+        let mut upload_time = None;
+        if self.image.is_none() {
+            if let Some(frame) = self.frame.as_mut().filter(|frame| frame.width > 0 && frame.height > 0) {
+                let upload_start = Instant::now();
+                let buffer = mem::take(&mut frame.buffer);
+                let image = ImageBuf::from_raw(buffer, ImageFormat::RgbaSeparate, frame.width, frame.height);
+                self.image = Some(image.to_image(ctx.render_ctx));
+                upload_time = Some(upload_start.elapsed());
+            }
+        }
+        if let Some(image) = &self.image {
+            ctx.draw_image(image, rect, InterpolationMode::Bilinear);
+        }
+
+        if let Some(stats) = &mut self.stats {
+            stats.record(start.elapsed(), upload_time, (self.width, self.height));
+        }
+    }
+}
+
+// Collected with --benchmark to show what caching the uploaded image saves.
+#[derive(Default)]
+struct PaintStats {
+    paints: u32,
+    uploads: u32,
+    paint_time: Duration,
+    upload_time: Duration,
+}
+
+impl PaintStats {
+    fn record(&mut self, paint_time: Duration, upload_time: Option<Duration>, (width, height): (usize, usize)) {
+        self.paints += 1;
+        self.paint_time += paint_time;
+        let Some(upload_time) = upload_time else {
+            return;
+        };
+        self.uploads += 1;
+        self.upload_time += upload_time;
+
+        // Without the cache every paint would have paid for an upload.
+        let average_upload = self.upload_time / self.uploads;
+        let saved = average_upload * (self.paints - self.uploads);
+        println!(
+            "{}x{}: upload {:.2} ms, paint {:.2} ms on average, {} of {} paints reused the image, saving {:.1} ms",
+            width,
+            height,
+            upload_time.as_secs_f64() * 1000.0,
+            (self.paint_time / self.paints).as_secs_f64() * 1000.0,
+            self.paints - self.uploads,
+            self.paints,
+            saved.as_secs_f64() * 1000.0,
+        );
+    }
+}
+
+// Base keyboard steps, multiplied by 10 with Shift held and by 0.1 with Alt held.
+const TRANSLATION_STEP: f64 = 0.05;
+// Degrees.
+const ROTATION_STEP: f64 = 5.0;
+const SCALE_STEP: f64 = 0.05;
+
+enum ScreenAxis {
+    Right,
+    Up,
+    View,
+}
+
+// What a key does, in units of the base steps.
+enum Nudge {
+    Translate(f64, f64),
+    Rotate(ScreenAxis, f64),
+    Scale(f64),
+    Reset,
+}
+
+// Arrows and WASD move, Q/E and numpad 7/9 roll, numpad 8/2 and 4/6 tilt and turn around the
+// screen axes, +/- scale and R resets the transform and the camera. Shortcuts with Ctrl are left
+// to the menus.
+fn key_nudge(key: &KeyEvent) -> Option<Nudge> {
+    if key.mods.ctrl() || key.mods.meta() {
+        return None;
+    }
+
+    // The numpad is matched by position so that it works with and without Num Lock.
+    let nudge = match key.code {
+        Code::Numpad8 => Some(Nudge::Rotate(ScreenAxis::Right, -1.0)),
+        Code::Numpad2 => Some(Nudge::Rotate(ScreenAxis::Right, 1.0)),
+        Code::Numpad4 => Some(Nudge::Rotate(ScreenAxis::Up, -1.0)),
+        Code::Numpad6 => Some(Nudge::Rotate(ScreenAxis::Up, 1.0)),
+        Code::Numpad7 => Some(Nudge::Rotate(ScreenAxis::View, 1.0)),
+        Code::Numpad9 => Some(Nudge::Rotate(ScreenAxis::View, -1.0)),
+        Code::NumpadAdd | Code::Equal => Some(Nudge::Scale(1.0)),
+        Code::NumpadSubtract | Code::Minus => Some(Nudge::Scale(-1.0)),
+        _ => None,
+    };
+    if nudge.is_some() {
+        return nudge;
+    }
+
+    match &key.key {
+        KbKey::ArrowLeft => Some(Nudge::Translate(-1.0, 0.0)),
+        KbKey::ArrowRight => Some(Nudge::Translate(1.0, 0.0)),
+        KbKey::ArrowUp => Some(Nudge::Translate(0.0, 1.0)),
+        KbKey::ArrowDown => Some(Nudge::Translate(0.0, -1.0)),
+        KbKey::Character(character) => match character.to_lowercase().as_str() {
+            "a" => Some(Nudge::Translate(-1.0, 0.0)),
+            "d" => Some(Nudge::Translate(1.0, 0.0)),
+            "w" => Some(Nudge::Translate(0.0, 1.0)),
+            "s" => Some(Nudge::Translate(0.0, -1.0)),
+            "q" => Some(Nudge::Rotate(ScreenAxis::View, 1.0)),
+            "e" => Some(Nudge::Rotate(ScreenAxis::View, -1.0)),
+            "+" => Some(Nudge::Scale(1.0)),
+            "-" => Some(Nudge::Scale(-1.0)),
+            "r" => Some(Nudge::Reset),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Shift makes a step ten times larger and Alt ten times smaller. On layouts where + is typed
+// with Shift, as Shift+= on US keyboards, Shift belongs to the key and leaves the step alone.
+fn nudge_step(key: &KeyEvent) -> f64 {
+    let shift_types_plus = key.code == Code::Equal && key.key == KbKey::Character("+".to_string());
+    if key.mods.shift() && !shift_types_plus {
+        10.0
+    } else if key.mods.alt() {
+        0.1
+    } else {
+        1.0
+    }
+}
+
+// Shows through wherever the background is transparent.
+fn paint_checkerboard(ctx: &mut PaintCtx) {
+    const CELL: f64 = 16.0;
+
+    let size = ctx.size();
+    ctx.fill(size.to_rect(), &Color::grey8(0x99));
+    for row in 0..(size.height / CELL).ceil() as usize {
+        for column in (row % 2..(size.width / CELL).ceil() as usize).step_by(2) {
+            let origin = Point::new(column as f64 * CELL, row as f64 * CELL);
+            ctx.fill(Rect::from_origin_size(origin, (CELL, CELL)), &Color::grey8(0x66));
+        }
     }
 }
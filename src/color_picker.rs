@@ -0,0 +1,207 @@
+use druid::text::ParseFormatter;
+use druid::widget::{Button, Flex, Label, Painter, Slider, TextBox};
+use druid::{lens, Color, Lens, LensExt, RenderContext, Widget, WidgetExt};
+use raycasting::scene::PALETTE;
+
+type Rgb = (f64, f64, f64);
+
+const PRESETS: [Rgb; 4] = [
+    (1.0, 1.0, 1.0),
+    (0.5, 0.5, 0.5),
+    (0.0, 0.0, 0.0),
+    (1.0, 0.0, 0.0),
+];
+
+pub fn color_picker() -> impl Widget<Rgb> {
+    Flex::column()
+        .with_child(
+            Painter::new(|ctx, data: &Rgb, _| {
+                let rect = ctx.size().to_rect();
+                ctx.fill(rect, &to_color(*data));
+            })
+            .fix_height(24.0)
+            .expand_width()
+        )
+        .with_child(build_channel_row("R:", lens!(Rgb, 0), lens!(Rgb, 0), 1.0))
+        .with_child(build_channel_row("G:", lens!(Rgb, 1), lens!(Rgb, 1), 1.0))
+        .with_child(build_channel_row("B:", lens!(Rgb, 2), lens!(Rgb, 2), 1.0))
+        .with_child(build_channel_row("H:", Hsv.then(lens!(Rgb, 0)), Hsv.then(lens!(Rgb, 0)), 360.0))
+        .with_child(build_channel_row("S:", Hsv.then(lens!(Rgb, 1)), Hsv.then(lens!(Rgb, 1)), 1.0))
+        .with_child(build_channel_row("V:", Hsv.then(lens!(Rgb, 2)), Hsv.then(lens!(Rgb, 2)), 1.0))
+        .with_child(build_presets())
+}
+
+fn build_channel_row(
+    text: &str,
+    lens_slider: impl Lens<Rgb, f64> + 'static,
+    lens_text_box: impl Lens<Rgb, f64> + 'static,
+    max: f64,
+) -> impl Widget<Rgb> {
+    Flex::row()
+        .with_child(
+            Label::new(text).fix_width(24.0)
+        )
+        .with_flex_child(
+            Slider::new()
+                .with_range(0.0, max)
+                .lens(lens_slider)
+                .expand_width(),
+            2.0
+        )
+        .with_flex_child(
+            TextBox::new()
+                .with_formatter(ParseFormatter::new())
+                .lens(lens_text_box.then(Rounded))
+                .expand_width(),
+            1.0
+        )
+        .expand_width()
+}
+
+fn build_presets() -> impl Widget<Rgb> {
+    PALETTE
+        .into_iter()
+        .chain(PRESETS)
+        .fold(Flex::row(), |row, preset| {
+            row.with_flex_child(
+                Button::from_label(Label::new(""))
+                    .background(to_color(preset))
+                    .on_click(move |_, data: &mut Rgb, _| *data = preset)
+                    .expand_width(),
+                1.0
+            )
+        })
+        .expand_width()
+}
+
+fn to_color(rgb: Rgb) -> Color {
+    Color::rgb(rgb.0, rgb.1, rgb.2)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+// Writes back only when the shown value is edited, so that merely displaying a channel never
+// replaces it with its rounded value.
+#[derive(Clone, Copy)]
+struct Rounded;
+
+impl Lens<f64, f64> for Rounded {
+    fn with<V, F: FnOnce(&f64) -> V>(&self, data: &f64, f: F) -> V {
+        f(&round(*data))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut f64) -> V>(&self, data: &mut f64, f: F) -> V {
+        let original = round(*data);
+        let mut value = original;
+        let result = f(&mut value);
+        if value != original {
+            *data = value;
+        }
+        result
+    }
+}
+
+// Views an RGB triple as (hue in degrees, saturation, value).
+#[derive(Clone, Copy)]
+struct Hsv;
+
+impl Lens<Rgb, Rgb> for Hsv {
+    fn with<V, F: FnOnce(&Rgb) -> V>(&self, data: &Rgb, f: F) -> V {
+        f(&rgb_to_hsv(*data))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut Rgb) -> V>(&self, data: &mut Rgb, f: F) -> V {
+        let original = rgb_to_hsv(*data);
+        let mut hsv = original;
+        let result = f(&mut hsv);
+        if hsv != original {
+            *data = hsv_to_rgb(hsv);
+        }
+        result
+    }
+}
+
+fn rgb_to_hsv((r, g, b): Rgb) -> Rgb {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let delta = max - min;
+
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let saturation = if max == 0.0 { 0.0 } else { delta / max };
+
+    (hue, saturation, max)
+}
+
+fn hsv_to_rgb((hue, saturation, value): Rgb) -> Rgb {
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let m = value - chroma;
+
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+
+    (r + m, g + m, b + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Rgb, expected: Rgb) {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(actual.0, expected.0) && close(actual.1, expected.1) && close(actual.2, expected.2), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn known_colors() {
+        let colors = [
+            ((0.0, 0.0, 0.0), (0.0, 0.0, 0.0)),
+            ((0.5, 0.5, 0.5), (0.0, 0.0, 0.5)),
+            ((1.0, 0.0, 0.0), (0.0, 1.0, 1.0)),
+            ((1.0, 1.0, 0.0), (60.0, 1.0, 1.0)),
+            ((0.0, 0.5, 0.0), (120.0, 1.0, 0.5)),
+            ((0.0, 1.0, 1.0), (180.0, 1.0, 1.0)),
+            ((0.0, 0.0, 1.0), (240.0, 1.0, 1.0)),
+            ((1.0, 0.0, 0.5), (330.0, 1.0, 1.0)),
+            ((1.0, 0.5, 0.5), (0.0, 0.5, 1.0)),
+        ];
+        for (rgb, hsv) in colors {
+            assert_close(rgb_to_hsv(rgb), hsv);
+            assert_close(hsv_to_rgb(hsv), rgb);
+        }
+    }
+
+    #[test]
+    fn hue_wraps_around() {
+        assert_close(hsv_to_rgb((360.0, 1.0, 1.0)), (1.0, 0.0, 0.0));
+        assert_close(hsv_to_rgb((-120.0, 1.0, 1.0)), (0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn round_trip() {
+        for rgb in PALETTE {
+            assert_close(hsv_to_rgb(rgb_to_hsv(rgb)), rgb);
+        }
+        for step in 0..=20 {
+            let rgb = (step as f64 / 20.0, 0.3, 1.0 - step as f64 / 40.0);
+            assert_close(hsv_to_rgb(rgb_to_hsv(rgb)), rgb);
+        }
+    }
+}
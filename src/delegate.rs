@@ -0,0 +1,124 @@
+use std::thread;
+use druid::{commands, AppDelegate, Command, DelegateCtx, Env, FileDialogOptions, FileInfo, FileSpec, Handled, LocalizedString, Menu, MenuItem, Selector, SysMods, Target, WindowId};
+use raycasting::image::{self, ImageFormat};
+use raycasting::{Renderer, SceneFile};
+use crate::AppState;
+
+pub const OPEN_SCENE: Selector<FileInfo> = Selector::new("raycasting.open-scene");
+pub const SAVE_SCENE: Selector<FileInfo> = Selector::new("raycasting.save-scene");
+pub const EXPORT_IMAGE: Selector<FileInfo> = Selector::new("raycasting.export-image");
+const EXPORT_FINISHED: Selector<String> = Selector::new("raycasting.export-finished");
+
+const SCENE_FILE: FileSpec = FileSpec::new("Scene", &["toml"]);
+const PNG_FILE: FileSpec = FileSpec::new("PNG image", &["png"]);
+
+pub struct Delegate;
+
+impl AppDelegate<AppState> for Delegate {
+    fn command(&mut self, ctx: &mut DelegateCtx, _target: Target, cmd: &Command, data: &mut AppState, _env: &Env) -> Handled {
+        if let Some(file_info) = cmd.get(OPEN_SCENE) {
+            let path = file_info.path();
+            match SceneFile::load(path) {
+                Ok(file) => {
+                    data.load_scene_file(file);
+                    data.status = format!("Opened {}", path.display());
+                }
+                Err(error) => data.status = format!("Failed to open {}: {}", path.display(), error),
+            }
+            return Handled::Yes;
+        }
+        if let Some(file_info) = cmd.get(SAVE_SCENE) {
+            let path = file_info.path();
+            data.status = match data.scene_file().save(path) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(error) => format!("Failed to save {}: {}", path.display(), error),
+            };
+            return Handled::Yes;
+        }
+        if let Some(file_info) = cmd.get(EXPORT_IMAGE) {
+            // A name typed without an extension gets the one of the dialog's only file type.
+            let mut path = file_info.path().to_path_buf();
+            if path.extension().is_none() {
+                path.set_extension("png");
+            }
+            let Some(format) = ImageFormat::from_path(&path) else {
+                data.status = format!("Failed to export {}: the file must end in .png or .ppm", path.display());
+                return Handled::Yes;
+            };
+            let scene = data.scene();
+            let width = data.export_width.max(1.0) as usize;
+            let height = data.export_height.max(1.0) as usize;
+            let threads = data.threads as usize;
+            let precision = data.precision;
+            data.status = format!("Exporting {}x{} to {}…", width, height, path.display());
+
+            // Large exports take seconds, so they run next to the interactive renderer.
+            let sink = ctx.get_external_handle();
+            thread::spawn(move || {
+                let mut renderer = Renderer::new();
+                renderer.set_threads(threads);
+                renderer.set_precision(precision);
+                let rgba = renderer.render(&scene, width, height);
+                let status = match image::save(&path, format, rgba, width, height) {
+                    Ok(()) => format!("Exported {}", path.display()),
+                    Err(error) => format!("Failed to export {}: {}", path.display(), error),
+                };
+                let _ = sink.submit_command(EXPORT_FINISHED, status, Target::Auto);
+            });
+            return Handled::Yes;
+        }
+        if let Some(status) = cmd.get(EXPORT_FINISHED) {
+            data.status = status.clone();
+            return Handled::Yes;
+        }
+        Handled::No
+    }
+}
+
+pub fn build_menu(_window: Option<WindowId>, _data: &AppState, _env: &Env) -> Menu<AppState> {
+    let scene_dialog = FileDialogOptions::new()
+        .allowed_types(vec![SCENE_FILE])
+        .default_type(SCENE_FILE);
+
+    Menu::empty()
+        .entry(
+            Menu::new(LocalizedString::new("common-menu-file-menu"))
+                .entry(
+                    MenuItem::new("Open scene…")
+                        .command(commands::SHOW_OPEN_PANEL.with(scene_dialog.clone().accept_command(OPEN_SCENE)))
+                        .hotkey(SysMods::Cmd, "o")
+                )
+                .entry(
+                    MenuItem::new("Save scene…")
+                        .command(commands::SHOW_SAVE_PANEL.with(scene_dialog.default_name("scene.toml").accept_command(SAVE_SCENE)))
+                        .hotkey(SysMods::Cmd, "s")
+                )
+                .separator()
+                .entry(
+                    MenuItem::new("Export image…")
+                        .command(commands::SHOW_SAVE_PANEL.with(export_dialog()))
+                        .hotkey(SysMods::Cmd, "e")
+                )
+        )
+        .entry(
+            Menu::new(LocalizedString::new("common-menu-edit-menu"))
+                .entry(
+                    MenuItem::new(LocalizedString::new("common-menu-undo"))
+                        .command(commands::UNDO)
+                        .hotkey(SysMods::Cmd, "z")
+                )
+                .entry(
+                    MenuItem::new(LocalizedString::new("common-menu-redo"))
+                        .command(commands::REDO)
+                        .hotkey(SysMods::CmdShift, "Z")
+                )
+        )
+}
+
+pub fn export_dialog() -> FileDialogOptions {
+    FileDialogOptions::new()
+        .allowed_types(vec![PNG_FILE])
+        .default_type(PNG_FILE)
+        .default_name("render.png")
+        .accept_command(EXPORT_IMAGE)
+}
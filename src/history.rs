@@ -0,0 +1,283 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use druid::widget::Controller;
+use druid::{commands, Env, Event, EventCtx, LifeCycle, LifeCycleCtx, Widget};
+use raycasting::Scene;
+use crate::AppState;
+
+const MAX_ENTRIES: usize = 100;
+// Wheel events closer together than this count as one scroll gesture.
+const WHEEL_GESTURE: Duration = Duration::from_millis(500);
+
+struct Entry {
+    label: String,
+    scene: Scene,
+}
+
+struct History {
+    entries: Vec<Entry>,
+    position: usize,
+    // Whether the entry at `position` belongs to a gesture that may still be going on.
+    open: bool,
+}
+
+impl History {
+    fn new(scene: Scene) -> Self {
+        History {
+            entries: vec![Entry { label: "Start".to_string(), scene }],
+            position: 0,
+            open: false,
+        }
+    }
+
+    fn current(&self) -> &Scene {
+        &self.entries[self.position].scene
+    }
+
+    fn record(&mut self, scene: Scene, merge: bool) {
+        if merge && self.open {
+            self.entries[self.position].scene = scene;
+            return;
+        }
+
+        let label = describe(self.current(), &scene);
+        self.entries.truncate(self.position + 1);
+        self.entries.push(Entry { label, scene });
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.remove(0);
+        }
+        self.position = self.entries.len() - 1;
+        self.open = true;
+    }
+
+    // Ends the open entry, so that the next change starts an entry of its own.
+    fn close(&mut self) {
+        self.open = false;
+    }
+
+    fn jump(&mut self, position: usize) -> Option<&Scene> {
+        if position >= self.entries.len() || position == self.position {
+            return None;
+        }
+        self.position = position;
+        self.close();
+        Some(self.current())
+    }
+
+    fn labels(&self) -> Vec<String> {
+        self.entries.iter().map(|entry| entry.label.clone()).collect()
+    }
+}
+
+// Names an edit after the first thing that differs between two scenes.
+fn describe(old: &Scene, new: &Scene) -> String {
+    if new.objects.len() > old.objects.len() {
+        return "Add object".to_string();
+    }
+    if new.objects.len() < old.objects.len() {
+        return "Remove object".to_string();
+    }
+    let changed = old.objects.iter().zip(&new.objects).position(|(old, new)| old != new);
+    if let Some(index) = changed {
+        let (old, new) = (&old.objects[index], &new.objects[index]);
+        let action = if old.surface != new.surface {
+            "Change surface of"
+        } else if (old.a, old.b, old.c) != (new.a, new.b, new.c) {
+            "Reshape"
+        } else if old.scale != new.scale {
+            "Scale"
+        } else if old.mirror != new.mirror {
+            "Toggle mirroring of"
+        } else if old.rotation != new.rotation {
+            "Rotate"
+        } else if old.translation != new.translation {
+            "Move"
+        } else {
+            "Recolour"
+        };
+        return format!("{} object {}", action, index + 1);
+    }
+    if old.camera != new.camera {
+        "Edit camera".to_string()
+    } else if old.lighting != new.lighting {
+        "Edit lighting".to_string()
+    } else {
+        "Change background".to_string()
+    }
+}
+
+// Wraps the whole window and records every change to the scene, whatever widget or command made it.
+// A mouse drag or a burst of wheel events becomes a single entry.
+pub struct HistoryController {
+    history: Option<History>,
+    buttons_down: bool,
+    last_wheel: Option<Instant>,
+}
+
+impl HistoryController {
+    pub fn new() -> Self {
+        HistoryController {
+            history: None,
+            buttons_down: false,
+            last_wheel: None,
+        }
+    }
+
+    // Whether the event continues the gesture of the last change, and whether it starts a new
+    // one. An event that starts a gesture closes the open entry, even if it changes nothing itself.
+    fn gesture(&mut self, event: &Event) -> (bool, bool) {
+        let (continues, starts) = match event {
+            Event::MouseDown(_) => {
+                self.buttons_down = true;
+                (false, true)
+            }
+            // Held buttons, such as those of a stepper, repeat on a timer.
+            Event::MouseMove(_) | Event::Timer(_) => (self.buttons_down, false),
+            Event::MouseUp(m) => {
+                let continues = self.buttons_down;
+                self.buttons_down = !m.buttons.is_empty();
+                (continues, false)
+            }
+            Event::Wheel(_) => {
+                let continues = self.last_wheel.is_some_and(|last| last.elapsed() < WHEEL_GESTURE);
+                self.last_wheel = Some(Instant::now());
+                (continues, !continues)
+            }
+            // Holding a key down repeats it, and the repeats belong to the first press.
+            Event::KeyDown(k) => (k.repeat, !k.repeat),
+            _ => (false, false),
+        };
+        if starts {
+            if let Some(history) = &mut self.history {
+                history.close();
+            }
+        }
+        (continues, starts)
+    }
+
+    fn restore(&mut self, position: usize, data: &mut AppState) {
+        if let Some(history) = &mut self.history {
+            if let Some(scene) = history.jump(position) {
+                data.set_scene(scene);
+            }
+            data.history_position = history.position;
+        }
+    }
+
+    fn record(&mut self, data: &mut AppState, merge: bool) -> bool {
+        let Some(history) = &mut self.history else {
+            return false;
+        };
+        let scene = data.scene();
+        if *history.current() == scene {
+            return false;
+        }
+        history.record(scene, merge);
+        data.history = Arc::new(history.labels());
+        data.history_position = history.position;
+        true
+    }
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for HistoryController {
+    fn event(&mut self, child: &mut W, ctx: &mut EventCtx, event: &Event, data: &mut AppState, env: &Env) {
+        if let Event::Command(command) = event {
+            let position = self.history.as_ref().map_or(0, |history| history.position);
+            if command.is(commands::UNDO) {
+                self.restore(position.saturating_sub(1), data);
+                ctx.set_handled();
+                return;
+            }
+            if command.is(commands::REDO) {
+                self.restore(position + 1, data);
+                ctx.set_handled();
+                return;
+            }
+        }
+
+        // Changes made outside the widget tree, such as opening a scene file, get their own entry.
+        if self.record(data, false) {
+            if let Some(history) = &mut self.history {
+                history.close();
+            }
+        }
+
+        let (continues, starts) = self.gesture(event);
+        child.event(ctx, event, data, env);
+
+        // The history list in the side panel is a radio group over positions.
+        if let Some(position) = self.history.as_ref().map(|history| history.position) {
+            if data.history_position != position {
+                self.restore(data.history_position, data);
+                return;
+            }
+        }
+        // Changes outside any gesture, such as a text box committing its value when the canvas
+        // takes the focus, get an entry of their own that a following drag does not join.
+        if self.record(data, continues) && !continues && !starts {
+            if let Some(history) = &mut self.history {
+                history.close();
+            }
+        }
+    }
+
+    fn lifecycle(&mut self, child: &mut W, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &AppState, env: &Env) {
+        if let LifeCycle::WidgetAdded = event {
+            self.history = Some(History::new(data.scene()));
+        }
+        child.lifecycle(ctx, event, data, env);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use druid::{Modifiers, MouseButton, MouseButtons, MouseEvent, Point, TimerToken, Vec2};
+
+    fn moved(x: f64) -> Scene {
+        let mut scene = Scene::new();
+        scene.objects[0].translation = (x, 0.0, 0.0);
+        scene
+    }
+
+    // Each drag starts with a mouse down, which closes the entry of the drag before it.
+    #[test]
+    fn drags_in_a_row_are_separate_entries() {
+        let mut history = History::new(Scene::new());
+        for drag in [[0.1, 0.2], [0.3, 0.4]] {
+            history.close();
+            for x in drag {
+                history.record(moved(x), true);
+            }
+        }
+        assert_eq!(history.labels(), ["Start", "Move object 1", "Move object 1"]);
+
+        let position = history.position - 1;
+        assert_eq!(history.jump(position), Some(&moved(0.2)));
+    }
+    fn mouse(buttons: MouseButtons) -> MouseEvent {
+        MouseEvent {
+            pos: Point::ZERO,
+            window_pos: Point::ZERO,
+            buttons,
+            mods: Modifiers::empty(),
+            count: 1,
+            focus: false,
+            button: MouseButton::Left,
+            wheel_delta: Vec2::ZERO,
+        }
+    }
+
+    // A held stepper repeats on a timer, which joins the change of its mouse down. Once the
+    // button is up, a timer or a command is no gesture at all.
+    #[test]
+    fn timers_continue_only_while_a_button_is_held() {
+        let mut controller = HistoryController::new();
+        let held = MouseButtons::new().with(MouseButton::Left);
+        assert_eq!(controller.gesture(&Event::MouseDown(mouse(held))), (false, true));
+        assert_eq!(controller.gesture(&Event::Timer(TimerToken::next())), (true, false));
+        assert_eq!(controller.gesture(&Event::MouseUp(mouse(MouseButtons::new()))), (true, false));
+        assert_eq!(controller.gesture(&Event::Timer(TimerToken::next())), (false, false));
+        assert_eq!(controller.gesture(&Event::Command(commands::UNDO.into())), (false, false));
+    }
+}
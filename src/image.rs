@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+pub enum ImageFormat {
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            _ => None,
+        }
+    }
+}
+
+pub fn save(path: &Path, format: ImageFormat, rgba: &[u8], width: usize, height: usize) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match format {
+        ImageFormat::Png => write_png(&mut writer, rgba, width, height)?,
+        ImageFormat::Ppm => write_ppm(&mut writer, rgba, width, height)?,
+    }
+    writer.flush()
+}
+
+pub fn write_ppm(writer: &mut impl Write, rgba: &[u8], width: usize, height: usize) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    for pixel in rgba.chunks_exact(4).take(width * height) {
+        writer.write_all(&pixel[..3])?;
+    }
+    Ok(())
+}
+
+pub fn write_png(writer: &mut impl Write, rgba: &[u8], width: usize, height: usize) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_string());
+    let (Ok(png_width), Ok(png_height)) = (u32::try_from(width), u32::try_from(height)) else {
+        return Err(invalid("image too large for PNG"));
+    };
+    if width == 0 || height == 0 {
+        return Err(invalid("PNG images need a width and height of at least 1"));
+    }
+    let pixels = rgba.get(..width * height * 4).ok_or_else(|| invalid("fewer pixels than the image size needs"))?;
+
+    let mut encoder = png::Encoder::new(writer, png_width, png_height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+    // Images are written once, so the slowest and smallest setting is worth it.
+    encoder.set_compression(png::Compression::Best);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(png: &[u8]) -> (png::OutputInfo, Vec<u8>) {
+        let mut reader = png::Decoder::new(png).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        pixels.truncate(info.buffer_size());
+        (info, pixels)
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let (width, height) = (5, 3);
+        let rgba = (0..width * height * 4).map(|i| (i * 13) as u8).collect::<Vec<_>>();
+        let mut png = Vec::new();
+        write_png(&mut png, &rgba, width, height).unwrap();
+
+        let (info, pixels) = decode(&png);
+        assert_eq!((info.width, info.height), (5, 3));
+        assert_eq!((info.color_type, info.bit_depth), (png::ColorType::Rgba, png::BitDepth::Eight));
+        assert_eq!(pixels, rgba);
+    }
+
+    #[test]
+    fn png_is_compressed() {
+        let (width, height) = (800, 600);
+        let rgba = [0, 0, 0, 255].repeat(width * height);
+        let mut png = Vec::new();
+        write_png(&mut png, &rgba, width, height).unwrap();
+        assert!(png.len() < 10_000, "{} bytes", png.len());
+        assert_eq!(decode(&png).1, rgba);
+    }
+
+    #[test]
+    fn png_refuses_bad_sizes() {
+        for (width, height, length) in [(0, 3, 0), (3, 0, 0), (2, 2, 15)] {
+            let error = write_png(&mut Vec::new(), &vec![0; length], width, height).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        }
+    }
+}
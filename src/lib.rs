@@ -0,0 +1,14 @@
+pub mod camera;
+pub mod image;
+pub mod lighting;
+pub mod renderer;
+pub mod scene;
+pub mod scene_file;
+pub mod surface;
+
+pub use camera::{Camera, Fit, Projection};
+pub use lighting::{Lighting, Reflection};
+pub use renderer::{Precision, Renderer, INCREMENTAL_TOLERANCE};
+pub use scene::{Object, Scene};
+pub use scene_file::SceneFile;
+pub use surface::Surface;
@@ -0,0 +1,83 @@
+use nalgebra::{convert, convert_unchecked, RealField, Vector3};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Reflection {
+    Phong,
+    BlinnPhong,
+}
+
+impl Reflection {
+    pub const ALL: [Reflection; 2] = [Reflection::Phong, Reflection::BlinnPhong];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Reflection::Phong => "Phong",
+            Reflection::BlinnPhong => "Blinn-Phong",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Reflection::Phong => "phong",
+            Reflection::BlinnPhong => "blinn-phong",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Reflection::ALL.into_iter().find(|reflection| reflection.key() == key)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Lighting {
+    pub reflection: Reflection,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: f64,
+    // Direction from the surface towards the light in world space, need not be normalized.
+    pub light_direction: (f64, f64, f64),
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Lighting::new()
+    }
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        Lighting {
+            reflection: Reflection::Phong,
+            ambient: 0.1,
+            diffuse: 0.7,
+            specular: 0.3,
+            shininess: 20.0,
+            light_direction: (-1.0, 1.0, 1.0),
+        }
+    }
+
+    // Diffuse and specular intensity at a point with unit normal n seen from unit direction v.
+    pub fn intensity<T: RealField + Copy>(&self, light: &Vector3<T>, n: &Vector3<T>, v: &Vector3<T>) -> (f64, f64) {
+        let n_dot_l = n.dot(light);
+        if n_dot_l <= T::zero() {
+            return (0.0, 0.0);
+        }
+
+        let highlight = match self.reflection {
+            Reflection::Phong => (n * (n_dot_l + n_dot_l) - light).dot(v),
+            Reflection::BlinnPhong => n.dot(&(light + v).normalize()),
+        };
+        let specular = highlight.max(T::zero()).powf(convert(self.shininess));
+
+        (convert_unchecked(n_dot_l), convert_unchecked(specular))
+    }
+
+    pub fn light<T: RealField + Copy>(&self) -> Vector3<T> {
+        let (x, y, z) = self.light_direction;
+        Vector3::new(x, y, z)
+            .map(convert::<f64, T>)
+            .try_normalize(T::default_epsilon())
+            .unwrap_or(Vector3::z())
+    }
+}
@@ -1,22 +1,105 @@
+mod bindings;
 mod canvas;
+mod color_picker;
+mod delegate;
+mod history;
+mod worker;
 
 use druid::widget::prelude::*;
-use druid::{AppLauncher, Lens, LocalizedString, UnitPoint, Widget, WidgetExt, WindowDesc};
-use druid::text::ParseFormatter;
-use druid::widget::{Container, Flex, Label, LensWrap, Stepper, TextBox};
+use druid::{commands, lens, AppLauncher, Color, Lens, LensExt, LocalizedString, UnitPoint, Widget, WidgetExt, WindowDesc};
+use druid::text::{Formatter, ParseFormatter, Selection, Validation, ValidationError};
+use std::env;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use druid::widget::{Button, Checkbox, Container, Flex, Label, LensWrap, LineBreaking, RadioGroup, Scroll, Stepper, Tabs, TextBox, ViewSwitcher};
+use nalgebra::UnitQuaternion;
+use raycasting::scene::{euler_to_quaternion, quaternion_to_euler, MIN_SCALE, PALETTE};
+use raycasting::{Camera, Fit, Lighting, Object, Precision, Projection, Reflection, Scene, SceneFile, Surface};
+use crate::bindings::Bindings;
 use crate::canvas::Canvas;
+use crate::color_picker::color_picker;
+use crate::delegate::{build_menu, export_dialog, Delegate};
+use crate::history::HistoryController;
+
+const WARNING_COLOR: Color = Color::rgb8(0xff, 0xa0, 0x40);
+const MAX_EXPORT_SIZE: f64 = 16384.0;
+
+// An object of the scene, compared as a whole like `Choice` so that the library type needs no
+// druid traits. Derefs to the object, and the side panel edits its fields through `SelectedObject`.
+#[derive(Clone, PartialEq)]
+struct SceneObject(Object);
+
+impl Data for SceneObject {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Deref for SceneObject {
+    type Target = Object;
+
+    fn deref(&self) -> &Object {
+        &self.0
+    }
+}
+
+impl DerefMut for SceneObject {
+    fn deref_mut(&mut self) -> &mut Object {
+        &mut self.0
+    }
+}
+
+impl SceneObject {
+    fn new(color: (f64, f64, f64)) -> Self {
+        SceneObject(Object { color, ..Object::new() })
+    }
+
+    // Scale gestures stop short of zero, and of negative values unless mirroring is enabled.
+    fn set_scale(&mut self, scale: (f64, f64, f64)) {
+        self.scale = limit_scale(self, scale);
+    }
+
+    // Turns the object about its own centre by a rotation given in world space.
+    fn rotate(&mut self, rotation: UnitQuaternion<f64>) {
+        self.rotation = rotation * self.rotation;
+        self.rotation.renormalize();
+    }
+}
 
 #[derive(Clone, Data, Lens)]
 struct AppState {
-    a: f64,
-    b: f64,
-    c: f64,
-    m: f64,
-    scale: (f64, f64, f64),
-    rotation: (f64, f64, f64),
-    translation: (f64, f64, f64),
+    objects: Arc<Vec<SceneObject>>,
+    selected: usize,
+    perspective: bool,
+    eye: (f64, f64, f64),
+    target: (f64, f64, f64),
+    fov: f64,
+    #[data(eq)]
+    fit: Fit,
+    #[data(eq)]
+    reflection: Reflection,
+    ambient: f64,
+    diffuse: f64,
+    specular: f64,
+    shininess: f64,
+    light_direction: (f64, f64, f64),
+    background: (f64, f64, f64),
+    transparent_background: bool,
+    threads: f64,
+    #[data(eq)]
+    precision: Precision,
     accuracy: usize,
     min_accuracy: usize,
+    max_timer: f64,
+    timer_step: f64,
+    full_accuracy: bool,
+    adaptive_refinement: bool,
+    target_frame_time: f64,
+    export_width: f64,
+    export_height: f64,
     right_button_clicked: bool,
     right_button_position: (f64, f64),
     ctrl_clicked: bool,
@@ -25,17 +108,40 @@ struct AppState {
     shift_clicked: bool,
     scroll_clicked: bool,
     scroll_position: (f64, f64),
+    status: String,
+    history: Arc<Vec<String>>,
+    history_position: usize,
 }
 
 impl AppState {
     fn new() -> Self {
         AppState { 
-            a: 1.0, b: 1.0, c: 1.0, m: 1.0,
-            scale: (1.0, 1.0, 1.0),
-            rotation: (0.0, 0.0, 0.0),
-            translation: (0.0, 0.0, 0.0),
+            objects: Arc::new(vec![SceneObject::new(PALETTE[0])]),
+            selected: 0,
+            perspective: false,
+            eye: (0.0, 0.0, 3.0),
+            target: (0.0, 0.0, 0.0),
+            fov: 45.0,
+            fit: Fit::Shorter,
+            reflection: Reflection::Phong,
+            ambient: 0.1,
+            diffuse: 0.7,
+            specular: 0.3,
+            shininess: 20.0,
+            light_direction: (-1.0, 1.0, 1.0),
+            background: (0.0, 0.0, 0.0),
+            transparent_background: false,
+            threads: 0.0,
+            precision: Precision::Single,
             accuracy: 1,
             min_accuracy: 32,
+            max_timer: 0.2,
+            timer_step: 0.1,
+            full_accuracy: false,
+            adaptive_refinement: true,
+            target_frame_time: 33.0,
+            export_width: 3840.0,
+            export_height: 2160.0,
             right_button_clicked: false,
             right_button_position: (0.0, 0.0),
             ctrl_clicked: false,
@@ -44,148 +150,548 @@ impl AppState {
             shift_clicked: false,
             scroll_clicked: false,
             scroll_position: (0.0, 0.0),
+            status: String::new(),
+            history: Arc::new(vec!["Start".to_string()]),
+            history_position: 0,
         }
     }
     
-    fn normalize_rotation(&mut self) {
-        while self.rotation.0 > std::f64::consts::PI {
-            self.rotation.0 -= 2.0 * std::f64::consts::PI;
+    fn camera(&self) -> Camera {
+        Camera {
+            projection: if self.perspective { Projection::Perspective } else { Projection::Orthographic },
+            eye: self.eye,
+            target: self.target,
+            fov: self.fov,
+            fit: self.fit,
         }
-        while self.rotation.0 < -std::f64::consts::PI {
-            self.rotation.0 += 2.0 * std::f64::consts::PI;
+    }
+
+    fn scene(&self) -> Scene {
+        Scene {
+            objects: self.objects.iter().map(|object| object.0).collect(),
+            camera: self.camera(),
+            lighting: Lighting {
+                reflection: self.reflection,
+                ambient: self.ambient,
+                diffuse: self.diffuse,
+                specular: self.specular,
+                shininess: self.shininess,
+                light_direction: self.light_direction,
+            },
+            background: (
+                self.background.0,
+                self.background.1,
+                self.background.2,
+                if self.transparent_background { 0.0 } else { 1.0 },
+            ),
         }
-        while self.rotation.1 > std::f64::consts::PI {
-            self.rotation.1 -= 2.0 * std::f64::consts::PI;
+    }
+
+    fn scene_file(&self) -> SceneFile {
+        SceneFile {
+            scene: self.scene(),
+            threads: self.threads as usize,
+            precision: self.precision,
+            min_accuracy: self.min_accuracy,
+            max_timer: self.max_timer,
+            timer_step: self.timer_step,
+            full_accuracy: self.full_accuracy,
+            adaptive_refinement: self.adaptive_refinement,
+            target_frame_time: self.target_frame_time,
         }
-        while self.rotation.1 < -std::f64::consts::PI {
-            self.rotation.1 += 2.0 * std::f64::consts::PI;
+    }
+
+    fn load_scene_file(&mut self, file: SceneFile) {
+        self.selected = 0;
+        self.set_scene(&file.scene);
+        self.threads = file.threads as f64;
+        self.precision = file.precision;
+        self.min_accuracy = file.min_accuracy;
+        self.max_timer = file.max_timer;
+        self.timer_step = file.timer_step;
+        self.full_accuracy = file.full_accuracy;
+        self.adaptive_refinement = file.adaptive_refinement;
+        self.target_frame_time = file.target_frame_time;
+    }
+
+    fn set_scene(&mut self, scene: &Scene) {
+        self.objects = Arc::new(scene.objects.iter().copied().map(SceneObject).collect());
+        self.selected = self.selected.min(self.objects.len() - 1);
+        self.perspective = scene.camera.projection == Projection::Perspective;
+        self.eye = scene.camera.eye;
+        self.target = scene.camera.target;
+        self.fov = scene.camera.fov;
+        self.fit = scene.camera.fit;
+        self.reflection = scene.lighting.reflection;
+        self.ambient = scene.lighting.ambient;
+        self.diffuse = scene.lighting.diffuse;
+        self.specular = scene.lighting.specular;
+        self.shininess = scene.lighting.shininess;
+        self.light_direction = scene.lighting.light_direction;
+        self.background = (scene.background.0, scene.background.1, scene.background.2);
+        self.transparent_background = scene.background.3 < 1.0;
+    }
+
+    // One line for every object that can't be drawn, which the renderer leaves out.
+    fn object_warnings(&self) -> String {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(index, object)| object.validate().err().map(|error| format!("Object {} is not drawn: {}", index + 1, error)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn selected_object_mut(&mut self) -> &mut SceneObject {
+        &mut Arc::make_mut(&mut self.objects)[self.selected]
+    }
+
+    fn add_object(&mut self) {
+        let color = PALETTE[self.objects.len() % PALETTE.len()];
+        Arc::make_mut(&mut self.objects).push(SceneObject::new(color));
+        self.selected = self.objects.len() - 1;
+    }
+
+    fn remove_object(&mut self) {
+        if self.objects.len() > 1 {
+            Arc::make_mut(&mut self.objects).remove(self.selected);
+            self.selected = self.selected.min(self.objects.len() - 1);
         }
-        while self.rotation.2 > std::f64::consts::PI {
-            self.rotation.2 -= 2.0 * std::f64::consts::PI;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SelectedObject;
+
+impl Lens<AppState, Object> for SelectedObject {
+    fn with<V, F: FnOnce(&Object) -> V>(&self, data: &AppState, f: F) -> V {
+        f(&data.objects[data.selected])
+    }
+
+    fn with_mut<V, F: FnOnce(&mut Object) -> V>(&self, data: &mut AppState, f: F) -> V {
+        let mut object = data.objects[data.selected].0;
+        let result = f(&mut object);
+        if object != data.objects[data.selected].0 {
+            data.selected_object_mut().0 = object;
         }
-        while self.rotation.2 < -std::f64::consts::PI {
-            self.rotation.2 += 2.0 * std::f64::consts::PI;
+        result
+    }
+}
+
+fn scale_allowed(value: f64, mirror: bool) -> bool {
+    value.is_finite() && value.abs() >= MIN_SCALE && (mirror || value > 0.0)
+}
+
+// The new scale, with every component that `Object::validate` would refuse kept at its old value.
+fn limit_scale(object: &Object, scale: (f64, f64, f64)) -> (f64, f64, f64) {
+    let limit = |old: f64, new: f64| if scale_allowed(new, object.mirror) { new } else { old };
+    (limit(object.scale.0, scale.0), limit(object.scale.1, scale.1), limit(object.scale.2, scale.2))
+}
+
+fn size_allowed(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
+
+fn export_size_allowed(value: f64) -> bool {
+    (1.0..=MAX_EXPORT_SIZE).contains(&value)
+}
+
+// The scale of an object, refusing the same values as `SceneObject::set_scale`. Catches what
+// the text box formatter lets through, such as stepper clicks towards zero.
+#[derive(Clone, Copy)]
+struct LimitedScale;
+
+impl Lens<Object, (f64, f64, f64)> for LimitedScale {
+    fn with<V, F: FnOnce(&(f64, f64, f64)) -> V>(&self, data: &Object, f: F) -> V {
+        f(&data.scale)
+    }
+
+    fn with_mut<V, F: FnOnce(&mut (f64, f64, f64)) -> V>(&self, data: &mut Object, f: F) -> V {
+        let mut scale = data.scale;
+        let result = f(&mut scale);
+        data.scale = limit_scale(data, scale);
+        result
+    }
+}
+
+// Shows a rotation as Euler angles in degrees, rounded so that typed values read back the way
+// they were entered. Editing an angle rebuilds the quaternion from all three.
+#[derive(Clone, Copy)]
+struct EulerDegrees;
+
+impl Lens<UnitQuaternion<f64>, (f64, f64, f64)> for EulerDegrees {
+    fn with<V, F: FnOnce(&(f64, f64, f64)) -> V>(&self, data: &UnitQuaternion<f64>, f: F) -> V {
+        f(&to_degrees(quaternion_to_euler(data)))
+    }
+
+    fn with_mut<V, F: FnOnce(&mut (f64, f64, f64)) -> V>(&self, data: &mut UnitQuaternion<f64>, f: F) -> V {
+        let original = to_degrees(quaternion_to_euler(data));
+        let mut degrees = original;
+        let result = f(&mut degrees);
+        if degrees != original {
+            *data = euler_to_quaternion((degrees.0.to_radians(), degrees.1.to_radians(), degrees.2.to_radians()));
         }
+        result
     }
 }
 
-fn build_ui() -> impl Widget<AppState> {
+fn to_degrees(radians: (f64, f64, f64)) -> (f64, f64, f64) {
+    let round = |radians: f64| (radians.to_degrees() * 1e6).round() / 1e6;
+    (round(radians.0), round(radians.1), round(radians.2))
+}
+
+fn build_ui(bindings: Bindings, benchmark: bool) -> impl Widget<AppState> {
     Flex::row()
-        .with_flex_child(Canvas::new().expand(), 5.0)
+        .with_flex_child(Canvas::new(bindings, benchmark).expand(), 5.0)
+        .with_flex_child(
+            Flex::column()
+                .with_flex_child(
+                    Tabs::new()
+                        .with_tab("Object", build_object_tab())
+                        .with_tab("Camera", build_camera_menu())
+                        .with_tab("Lighting", build_lighting_menu())
+                        .with_tab("Colours", build_colors_tab())
+                        .with_tab("Render", build_render_tab())
+                        .with_tab("History", build_history_tab())
+                        .expand(),
+                    1.0
+                )
+                .with_child(
+                    Label::dynamic(|data: &AppState, _| data.status.clone())
+                        .with_line_break_mode(LineBreaking::WordWrap)
+                        .expand_width()
+                ),
+            2.0
+        )
+        .controller(HistoryController::new())
+}
+
+fn build_object_tab() -> impl Widget<AppState> {
+    Flex::column()
+        .with_flex_child(
+            Container::new(
+                Flex::column()
+                    .with_child(
+                        build_object_menu()
+                    )
+                    .with_child(
+                        RadioGroup::column(Surface::ALL.map(|surface| (surface.name(), Choice(surface))))
+                            .lens(SelectedObject.then(lens!(Object, surface)).map(|surface| Choice(*surface), |surface, choice| *surface = choice.0))
+                            .expand_width()
+                    )
+                    .with_flex_child(
+                        ViewSwitcher::new(
+                            |data: &AppState, _| Choice(data.objects[data.selected].surface),
+                            |choice, _, _| Box::new(build_surface_menu(choice.0)),
+                        ),
+                        1.0
+                    )
+            ).expand(),
+            1.0
+        )
         .with_flex_child(
             Flex::column()
+                .with_child(
+                    Label::dynamic(|data: &AppState, _| data.object_warnings())
+                        .with_text_color(WARNING_COLOR)
+                        .with_line_break_mode(LineBreaking::WordWrap)
+                        .expand_width()
+                )
+                .with_flex_child(
+                    // Rebuilt when mirroring is switched, since that changes which values the fields accept.
+                    ViewSwitcher::new(
+                        |data: &AppState, _| data.objects[data.selected].mirror,
+                        |mirror, _, _| if *mirror {
+                            Box::new(build_limited_vector_menu("scale", SelectedObject.then(LimitedScale), (-10.0, 10.0), 0.1, |value| scale_allowed(value, true)))
+                        } else {
+                            Box::new(build_limited_vector_menu("scale", SelectedObject.then(LimitedScale), (0.01, 10.0), 0.1, |value| scale_allowed(value, false)))
+                        },
+                    ),
+                    3.0
+                )
+                .with_child(
+                    Checkbox::new("Allow mirroring (negative scale)")
+                        .lens(SelectedObject.then(lens!(Object, mirror)))
+                        .expand_width()
+                )
+                .with_flex_child(
+                    build_vector_menu("rotation", SelectedObject.map(|object| object.rotation, |object, rotation| object.rotation = rotation).then(EulerDegrees), (-180.0, 180.0), 5.0),
+                    3.0
+                )
+                .with_flex_child(
+                    build_vector_menu("translation", SelectedObject.map(|object| object.translation, |object, translation| object.translation = translation), (-10.0, 10.0), 0.1),
+                    3.0
+                ),
+            1.0
+        )
+}
+
+fn build_colors_tab() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Label::new("Object colour").expand_width()
+        )
+        .with_child(
+            color_picker().lens(SelectedObject.then(lens!(Object, color)))
+        )
+        .with_spacer(16.0)
+        .with_child(
+            Label::new("Background").expand_width()
+        )
+        .with_child(
+            color_picker().lens(AppState::background)
+        )
+        .with_child(
+            Checkbox::new("Transparent background")
+                .lens(AppState::transparent_background)
+                .expand_width()
+        )
+}
+
+fn build_render_tab() -> impl Widget<AppState> {
+    Flex::column()
+        .with_flex_child(
+            build_variable_menu("threads:", AppState::threads, AppState::threads, (0.0, 64.0), 1.0),
+            1.0
+        )
+        .with_child(
+            RadioGroup::row(Precision::ALL.map(|precision| (precision.name(), Choice(precision))))
+                .lens(AppState::precision.map(|precision| Choice(*precision), |precision, choice| *precision = choice.0))
+                .expand_width()
+        )
+        .with_flex_child(
+            LensWrap::new(
+                Label::dynamic(|data: &usize, _| format!("Accuracy: {}", data)).expand_width(),
+                AppState::accuracy,
+            ).expand(),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu(
+                "min accuracy:",
+                AppState::min_accuracy.map(|accuracy| *accuracy as f64, |accuracy, value| *accuracy = (value as usize).max(1)),
+                AppState::min_accuracy.map(|accuracy| *accuracy as f64, |accuracy, value| *accuracy = (value as usize).max(1)),
+                (1.0, 256.0),
+                1.0
+            ),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu("refine after (s):", AppState::max_timer, AppState::max_timer, (0.0, 5.0), 0.05),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu("timer step (s):", AppState::timer_step, AppState::timer_step, (0.01, 1.0), 0.01),
+            1.0
+        )
+        .with_child(
+            Checkbox::new("Always render at full accuracy")
+                .lens(AppState::full_accuracy)
+                .expand_width()
+        )
+        .with_child(
+            Checkbox::new("Adapt accuracy to frame time")
+                .lens(AppState::adaptive_refinement)
+                .expand_width()
+        )
+        .with_flex_child(
+            build_variable_menu("target frame (ms):", AppState::target_frame_time, AppState::target_frame_time, (1.0, 1000.0), 1.0),
+            1.0
+        )
+        .with_flex_child(
+            build_limited_menu("export width:", AppState::export_width, AppState::export_width, (1.0, MAX_EXPORT_SIZE), 1.0, export_size_allowed),
+            1.0
+        )
+        .with_flex_child(
+            build_limited_menu("export height:", AppState::export_height, AppState::export_height, (1.0, MAX_EXPORT_SIZE), 1.0, export_size_allowed),
+            1.0
+        )
+        .with_child(
+            Button::new("Export image…")
+                .on_click(|ctx, _, _| ctx.submit_command(commands::SHOW_SAVE_PANEL.with(export_dialog())))
+                .expand_width()
+        )
+        .with_flex_spacer(3.0)
+}
+
+fn build_history_tab() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Flex::row()
                 .with_flex_child(
-                    Container::new(
-                        Flex::column()
-                            .with_flex_child(
-                                build_variable_menu("a:", AppState::a, AppState::a, (0.1, 10.0), 0.1),
-                                1.0
-                            )
-                            .with_flex_child(
-                                build_variable_menu("b:", AppState::b, AppState::b, (0.1, 10.0), 0.1),
-                                1.0
-                            )
-                            .with_flex_child(
-                                build_variable_menu("c:", AppState::c, AppState::c, (0.1, 10.0), 0.1),
-                                1.0
-                            )
-                            .with_flex_child(
-                                build_variable_menu("m:", AppState::m, AppState::m, (1.0, 100.0), 1.0),
-                                1.0
-                            )
-                    ).expand(),
+                    Button::new("Undo")
+                        .on_click(|ctx, _, _| ctx.submit_command(commands::UNDO))
+                        .disabled_if(|data: &AppState, _| data.history_position == 0)
+                        .expand_width(),
                     1.0
                 )
                 .with_flex_child(
-                    Container::new(
-                        Flex::column()
-                            .with_flex_child(
-                                Container::new(
-                                    LensWrap::new(
-                                        Flex::column()
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("ScaleX: {}", data.0)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("ScaleY: {}", data.1)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("ScaleZ: {}", data.2)).expand_width(),
-                                                1.0
-                                            ),
-                                        AppState::scale,
-                                    ),
-                                ).expand(),
-                                3.0
-                            )
-                            .with_flex_child(
-                                Container::new(
-                                    LensWrap::new(
-                                        Flex::column()
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("RotationX: {}", data.0)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("RotationY: {}", data.1)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("RotationZ: {}", data.2)).expand_width(),
-                                                1.0
-                                            ),
-                                        AppState::rotation,
-                                    ),
-                                ).expand(),
-                                3.0
-                            )
-                            .with_flex_child(
-                                Container::new(
-                                    LensWrap::new(
-                                        Flex::column()
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("TranslationX: {}", data.0)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("TranslationY: {}", data.1)).expand_width(),
-                                                1.0
-                                            )
-                                            .with_flex_child(
-                                                Label::dynamic(|data: &(f64, f64, f64), _| format!("TranslationZ: {}", data.2)).expand_width(),
-                                                1.0
-                                            ),
-                                        AppState::translation,
-                                    ),
-                                ).expand(),
-                                3.0
-                            )
-                            .with_flex_child(
-                                Flex::column()
-                                    .with_flex_child(
-                                        LensWrap::new(
-                                            Label::dynamic(|data: &usize, _| format!("Accuracy: {}", data)).expand_width(),
-                                            AppState::accuracy,
-                                        ).expand(),
-                                        1.0,
-                                    )
-                                    .with_flex_child(
-                                        LensWrap::new(
-                                            Label::dynamic(|data: &usize, _| format!("Min accuracy: {}", data)).expand_width(),
-                                            AppState::min_accuracy,
-                                        ).expand(),
-                                        1.0,
-                                    )
-                                    .expand(),
-                                2.0
-                            )
-                            .expand()
-                    ).expand_width(),
+                    Button::new("Redo")
+                        .on_click(|ctx, _, _| ctx.submit_command(commands::REDO))
+                        .disabled_if(|data: &AppState, _| data.history_position + 1 >= data.history.len())
+                        .expand_width(),
                     1.0
                 )
-                .expand(),
+        )
+        .with_flex_child(
+            ViewSwitcher::new(
+                |data: &AppState, _| data.history.clone(),
+                |history, _, _| Box::new(
+                    Scroll::new(
+                        RadioGroup::column(history.iter().cloned().enumerate().map(|(index, label)| (label, index)).collect::<Vec<_>>())
+                            .lens(AppState::history_position)
+                            .expand_width()
+                    ).vertical()
+                ),
+            ),
+            1.0
+        )
+}
+
+fn build_object_menu() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Button::new("Add object")
+                        .on_click(|_, data: &mut AppState, _| data.add_object())
+                        .expand_width(),
+                    1.0
+                )
+                .with_flex_child(
+                    Button::new("Remove object")
+                        .on_click(|_, data: &mut AppState, _| data.remove_object())
+                        .disabled_if(|data: &AppState, _| data.objects.len() <= 1)
+                        .expand_width(),
+                    1.0
+                )
+        )
+        .with_child(
+            ViewSwitcher::new(
+                |data: &AppState, _| data.objects.len(),
+                |count, _, _| Box::new(
+                    RadioGroup::column((0..*count).map(|index| (format!("Object {}", index + 1), index)))
+                        .lens(AppState::selected)
+                        .expand_width()
+                ),
+            )
+        )
+}
+
+// Lets enums from the renderer library, which know nothing about druid, drive radio groups.
+#[derive(Clone, Copy, PartialEq)]
+struct Choice<T>(T);
+
+impl<T: Clone + PartialEq + 'static> Data for Choice<T> {
+    fn same(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+fn build_surface_menu(surface: Surface) -> impl Widget<AppState> {
+    let mut menu = Flex::column()
+        .with_child(
+            Label::new(surface.equation()).expand_width()
+        )
+        .with_flex_child(
+            build_limited_menu("a:", SelectedObject.then(lens!(Object, a)), SelectedObject.then(lens!(Object, a)), (0.1, 10.0), 0.1, size_allowed),
+            1.0
+        )
+        .with_flex_child(
+            build_limited_menu("b:", SelectedObject.then(lens!(Object, b)), SelectedObject.then(lens!(Object, b)), (0.1, 10.0), 0.1, size_allowed),
+            1.0
+        );
+    if surface.uses_c() {
+        menu.add_flex_child(
+            build_limited_menu("c:", SelectedObject.then(lens!(Object, c)), SelectedObject.then(lens!(Object, c)), (0.1, 10.0), 0.1, size_allowed),
+            1.0
+        );
+    }
+    menu
+}
+
+fn build_camera_menu() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Checkbox::new("Perspective")
+                .lens(AppState::perspective)
+                .expand_width()
+        )
+        .with_child(
+            RadioGroup::column(Fit::ALL.map(|fit| (fit.name(), Choice(fit))))
+                .lens(AppState::fit.map(|fit| Choice(*fit), |fit, choice| *fit = choice.0))
+                .expand_width()
+        )
+        .with_flex_child(
+            build_limited_menu("fov:", AppState::fov, AppState::fov, (1.0, 179.0), 1.0, Camera::fov_allowed),
+            1.0
+        )
+        .with_flex_child(
+            build_vector_menu("eye", AppState::eye, (-100.0, 100.0), 0.1),
+            3.0
+        )
+        .with_flex_child(
+            build_vector_menu("target", AppState::target, (-100.0, 100.0), 0.1),
+            3.0
+        )
+}
+
+fn build_lighting_menu() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            RadioGroup::row(Reflection::ALL.map(|reflection| (reflection.name(), Choice(reflection))))
+                .lens(AppState::reflection.map(|reflection| Choice(*reflection), |reflection, choice| *reflection = choice.0))
+                .expand_width()
+        )
+        .with_flex_child(
+            build_variable_menu("ambient:", AppState::ambient, AppState::ambient, (0.0, 1.0), 0.05),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu("diffuse:", AppState::diffuse, AppState::diffuse, (0.0, 1.0), 0.05),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu("specular:", AppState::specular, AppState::specular, (0.0, 1.0), 0.05),
+            1.0
+        )
+        .with_flex_child(
+            build_variable_menu("shininess:", AppState::shininess, AppState::shininess, (1.0, 200.0), 1.0),
+            1.0
+        )
+        .with_flex_child(
+            build_vector_menu("light", AppState::light_direction, (-10.0, 10.0), 0.1),
+            3.0
+        )
+}
+
+fn build_vector_menu(
+    text: &str,
+    lens: impl Lens<AppState, (f64, f64, f64)> + Clone + 'static,
+    range: (f64, f64),
+    step: f64,
+) -> impl Widget<AppState> {
+    build_limited_vector_menu(text, lens, range, step, |_| true)
+}
+
+fn build_limited_vector_menu(
+    text: &str,
+    lens: impl Lens<AppState, (f64, f64, f64)> + Clone + 'static,
+    range: (f64, f64),
+    step: f64,
+    allowed: fn(f64) -> bool,
+) -> impl Widget<AppState> {
+    Flex::column()
+        .with_flex_child(
+            build_limited_menu(&format!("{} x:", text), lens.clone().then(lens!((f64, f64, f64), 0)), lens.clone().then(lens!((f64, f64, f64), 0)), range, step, allowed),
+            1.0
+        )
+        .with_flex_child(
+            build_limited_menu(&format!("{} y:", text), lens.clone().then(lens!((f64, f64, f64), 1)), lens.clone().then(lens!((f64, f64, f64), 1)), range, step, allowed),
+            1.0
+        )
+        .with_flex_child(
+            build_limited_menu(&format!("{} z:", text), lens.clone().then(lens!((f64, f64, f64), 2)), lens.then(lens!((f64, f64, f64), 2)), range, step, allowed),
             1.0
         )
 }
@@ -196,6 +702,19 @@ fn build_variable_menu(
     lens_stepper: impl Lens<AppState, f64> + 'static,
     range: (f64, f64),
     step: f64,
+) -> impl Widget<AppState> {
+    build_limited_menu(text, lens_text_box, lens_stepper, range, step, |_| true)
+}
+
+// Typed values that `allowed` refuses are never stored; the text box keeps them selected
+// until they are corrected.
+fn build_limited_menu(
+    text: &str,
+    lens_text_box: impl Lens<AppState, f64> + 'static,
+    lens_stepper: impl Lens<AppState, f64> + 'static,
+    range: (f64, f64),
+    step: f64,
+    allowed: fn(f64) -> bool,
 ) -> impl Widget<AppState> {
     Flex::row()
         .with_child(
@@ -203,7 +722,7 @@ fn build_variable_menu(
         )
         .with_flex_child(
             TextBox::new()
-                .with_formatter(ParseFormatter::new())
+                .with_formatter(Limited { parse: ParseFormatter::new(), allowed })
                 .lens(lens_text_box)
                 .expand_width(),
             1.0
@@ -218,17 +737,98 @@ fn build_variable_menu(
         .align_vertical(UnitPoint::TOP)
 }
 
+// Parses numbers like `ParseFormatter`, refusing those that `allowed` rejects.
+struct Limited {
+    parse: ParseFormatter<f64>,
+    allowed: fn(f64) -> bool,
+}
+
+impl Formatter<f64> for Limited {
+    fn format(&self, value: &f64) -> String {
+        self.parse.format(value)
+    }
+
+    fn validate_partial_input(&self, input: &str, selection: &Selection) -> Validation {
+        self.parse.validate_partial_input(input, selection)
+    }
+
+    fn value(&self, input: &str) -> Result<f64, ValidationError> {
+        let value = self.parse.value(input)?;
+        if (self.allowed)(value) {
+            Ok(value)
+        } else {
+            Err(ValidationError::new(NotAllowed(value)))
+        }
+    }
+}
+
+#[derive(Debug)]
+struct NotAllowed(f64);
+
+impl fmt::Display for NotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not allowed here", self.0)
+    }
+}
+
+impl std::error::Error for NotAllowed {}
+
+const USAGE: &str = "Usage: raycasting [--scene <FILE>] [--bindings <FILE>] [--benchmark]";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
 fn main() {
     let width = 800usize;
     let height = 600usize;
 
-    let main_window = WindowDesc::new(build_ui())
+    let mut initial_state = AppState::new();
+
+    let mut scene_path = None;
+    let mut bindings_path = None;
+    let mut benchmark = false;
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "--benchmark" {
+            benchmark = true;
+            continue;
+        }
+        let path = match flag.as_str() {
+            "--scene" => &mut scene_path,
+            "--bindings" => &mut bindings_path,
+            _ => usage(),
+        };
+        *path = Some(PathBuf::from(args.next().unwrap_or_else(|| usage())));
+    }
+
+    let mut errors = Vec::new();
+    if let Some(path) = scene_path {
+        match SceneFile::load(&path) {
+            Ok(file) => initial_state.load_scene_file(file),
+            Err(error) => errors.push(format!("Failed to open {}: {}", path.display(), error)),
+        }
+    }
+
+    // An explicitly given bindings file has to exist, the one in the config directory is optional.
+    let bindings_path = bindings_path.or_else(|| Bindings::default_path().filter(|path| path.exists()));
+    let bindings = match bindings_path {
+        None => Bindings::new(),
+        Some(path) => Bindings::load(&path).unwrap_or_else(|error| {
+            errors.push(format!("Failed to load bindings from {}: {}", path.display(), error));
+            Bindings::new()
+        }),
+    };
+    initial_state.status = errors.join("\n");
+
+    let main_window = WindowDesc::new(build_ui(bindings, benchmark))
         .title(LocalizedString::new("Raycasting"))
+        .menu(build_menu)
         .window_size((width as f64, height as f64));
 
-    let initial_state = AppState::new();
-
     AppLauncher::with_window(main_window)
+        .delegate(Delegate)
         .log_to_console()
         .launch(initial_state)
         .expect("Failed to launch application");
@@ -0,0 +1,786 @@
+use std::ops::Range;
+use std::thread;
+use nalgebra::{convert, Matrix2, Matrix3, Matrix4, Matrix4x3, RealField, Vector2, Vector3, Vector4};
+use crate::camera::{Projection, Rays};
+use crate::lighting::Lighting;
+use crate::scene::Scene;
+
+// Floating point type the rays are traced in. Single is faster; double keeps very flat or
+// distant surfaces from breaking up.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Precision {
+    Single,
+    Double,
+}
+
+impl Precision {
+    pub const ALL: [Precision; 2] = [Precision::Single, Precision::Double];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Precision::Single => "Single precision",
+            Precision::Double => "Double precision",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Precision::Single => "single",
+            Precision::Double => "double",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Precision::ALL.into_iter().find(|precision| precision.key() == key)
+    }
+}
+
+// Largest fraction of pixels incremental evaluation may change by more than 1 in a channel.
+// This is not a bound the arithmetic guarantees; the renderer tests and the benchmark binary
+// hold the sample scenes to it.
+pub const INCREMENTAL_TOLERANCE: f64 = 1e-3;
+
+pub struct Renderer {
+    canvas: Vec<u8>,
+    pixels_computed: Vec<bool>,
+    scene: Scene,
+    width: usize,
+    height: usize,
+
+    drawn_accuracy: usize,
+    accuracy: usize,
+    pixels_shaded: usize,
+    redrawn: bool,
+
+    threads: usize,
+    precision: Precision,
+    incremental: bool,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Renderer::new()
+    }
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer {
+            canvas: Vec::new(),
+            pixels_computed: Vec::new(),
+            scene: Scene::new(),
+            width: 0,
+            height: 0,
+
+            drawn_accuracy: 0,
+            accuracy: 1,
+            pixels_shaded: 0,
+            redrawn: false,
+
+            threads: 0,
+            precision: Precision::Single,
+            incremental: true,
+        }
+    }
+
+    pub fn buffer(&self) -> &[u8] {
+        &self.canvas
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn accuracy(&self) -> usize {
+        self.accuracy
+    }
+
+    // Pixels the last draw actually traced, as opposed to copied or kept from a coarser pass.
+    // Together with the time a pass took it gives the cost of a pixel on this machine.
+    pub fn pixels_shaded(&self) -> usize {
+        self.pixels_shaded
+    }
+
+    // Whether the last draw wrote the buffer. A draw at the accuracy already drawn leaves it as it was.
+    pub fn redrawn(&self) -> bool {
+        self.redrawn
+    }
+
+    pub fn threads(&self) -> usize {
+        if self.threads > 0 {
+            self.threads
+        } else {
+            thread::available_parallelism().map_or(1, |threads| threads.get())
+        }
+    }
+
+    // 0 uses one thread per available core.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads;
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    // Switching precision throws away the pixels computed so far.
+    pub fn set_precision(&mut self, precision: Precision) {
+        if self.precision != precision {
+            self.precision = precision;
+            self.pixels_computed.fill(false);
+            self.drawn_accuracy = 0;
+        }
+    }
+
+    pub fn incremental(&self) -> bool {
+        self.incremental
+    }
+
+    // With incremental evaluation the ray equation of each object is expanded once per row into
+    // polynomials in x, leaving a few multiplications per pixel instead of three matrix products.
+    // Colours then match the per-pixel evaluation to within 1 in every channel, except at a
+    // silhouette, where rounding may tip a pixel to the other side of the edge.
+    pub fn set_incremental(&mut self, incremental: bool) {
+        if self.incremental != incremental {
+            self.incremental = incremental;
+            self.pixels_computed.fill(false);
+            self.drawn_accuracy = 0;
+        }
+    }
+
+    // Only every accuracy-th pixel in each direction is computed, the rest are copied from it.
+    pub fn set_accuracy(&mut self, accuracy: usize) {
+        self.accuracy = accuracy.max(1);
+    }
+
+    pub fn draw(&mut self, scene: &Scene, width: usize, height: usize) -> bool {
+        self.draw_cancellable(scene, width, height, &|| false)
+    }
+
+    // Returns false if `cancelled` fired before the pass finished. Pixels computed so far are
+    // kept, so drawing the same parameters again continues where the cancelled pass stopped.
+    pub fn draw_cancellable(&mut self, scene: &Scene, width: usize, height: usize, cancelled: &(dyn Fn() -> bool + Sync)) -> bool {
+        if self.update(scene, width, height) {
+            self.pixels_computed.fill(false);
+            self.drawn_accuracy = 0;
+        }
+        self.redrawn = self.drawn_accuracy != self.accuracy;
+        if !self.redrawn {
+            self.pixels_shaded = 0;
+            return true;
+        }
+
+        let Some(pixels_shaded) = self.draw_pixels(cancelled) else {
+            self.drawn_accuracy = 0;
+            return false;
+        };
+        self.pixels_shaded = pixels_shaded;
+        self.drawn_accuracy = self.accuracy;
+        true
+    }
+
+    pub fn render(&mut self, scene: &Scene, width: usize, height: usize) -> &[u8] {
+        self.update(scene, width, height);
+        self.pixels_computed.fill(false);
+        self.set_accuracy(1);
+
+        self.pixels_shaded = self.draw_pixels(&|| false).unwrap_or(0);
+        self.drawn_accuracy = 1;
+        self.redrawn = true;
+
+        &self.canvas
+    }
+
+    fn update(&mut self, scene: &Scene, width: usize, height: usize) -> bool {
+        let result = self.scene != *scene
+            || self.width != width
+            || self.height != height;
+
+        if self.scene != *scene {
+            self.scene = scene.clone();
+        }
+        self.width = width;
+        self.height = height;
+
+        result
+    }
+
+    // Returns the number of pixels shaded, or None if cancelled.
+    fn draw_pixels(&mut self, cancelled: &(dyn Fn() -> bool + Sync)) -> Option<usize> {
+        let width = self.width;
+        let height = self.height;
+
+        self.canvas.resize(width * height * 4, 0);
+        self.pixels_computed.resize(width * height, false);
+
+        if width == 0 || height == 0 {
+            return Some(0);
+        }
+
+        match self.precision {
+            Precision::Single => self.draw_pass(self.pass::<f32>(), cancelled),
+            Precision::Double => self.draw_pass(self.pass::<f64>(), cancelled),
+        }
+    }
+
+    fn pass<T: RealField + Copy>(&self) -> Pass<T> {
+        let pixel_scale = self.scene.camera.pixel_scale(self.width, self.height);
+        // Bounds are always worked out in double precision; they are computed once per pass.
+        let rays = self.scene.camera.rays::<f64>();
+        let (eye, view) = rays.ray(0.0, 0.0);
+        let (eye, view) = (eye.xyz(), view.xyz());
+        let objects = self.scene.objects
+            .iter()
+            .filter_map(|object| {
+                // The form is built once in double precision, so the bounds and the trace
+                // always agree on which objects can be drawn.
+                let form = object.get_d::<f64>()?;
+                let (columns, rows) = match screen_bounds(&form, &rays) {
+                    Some((x, y)) => pixel_bounds(x, y, self.width, self.height, pixel_scale),
+                    None => (0..self.width, 0..self.height),
+                };
+                let (x, y, z) = object.translation;
+                let centre = Vector3::new(x, y, z);
+                let shift = (centre - eye).dot(&view) / view.norm_squared();
+                // The same quadric in coordinates centred on the object.
+                let to_world = Matrix4::new_translation(&centre);
+                let d = (to_world.transpose() * form * to_world).map(convert::<f64, T>);
+                let gradient = d + d.transpose();
+                Some(Prepared {
+                    d,
+                    symmetric: gradient / convert::<f64, T>(2.0),
+                    gradient,
+                    centre: centre.push(0.0).map(convert),
+                    shift: convert(shift),
+                    color: object.color,
+                    columns,
+                    rows,
+                })
+            })
+            .collect::<Vec<_>>();
+        Pass {
+            width: self.width,
+            height: self.height,
+            accuracy: self.accuracy,
+            pixel_scale: convert(pixel_scale),
+            objects,
+            background: self.scene.background,
+            lighting: self.scene.lighting,
+            light: self.scene.lighting.light(),
+            rays: self.scene.camera.rays(),
+            incremental: self.incremental,
+        }
+    }
+
+    fn draw_pass<T: RealField + Copy>(&mut self, pass: Pass<T>, cancelled: &(dyn Fn() -> bool + Sync)) -> Option<usize> {
+        let (width, height) = (pass.width, pass.height);
+        let pass = &pass;
+
+        // Bands start on a multiple of the accuracy, so every coarse pixel is copied
+        // from a sample that lives in the same band.
+        let threads = self.threads().min(height);
+        let rows_per_band = height.div_ceil(threads).div_ceil(pass.accuracy) * pass.accuracy;
+        let band_length = rows_per_band * width;
+
+        let bands = self.canvas
+            .chunks_mut(band_length * 4)
+            .zip(self.pixels_computed.chunks_mut(band_length))
+            .enumerate();
+
+        if threads == 1 {
+            return bands
+                .map(|(band, (canvas, pixels_computed))| draw_band(canvas, pixels_computed, band * rows_per_band, pass, cancelled))
+                .sum();
+        }
+
+        thread::scope(|scope| {
+            bands
+                .map(|(band, (canvas, pixels_computed))|
+                    scope.spawn(move || draw_band(canvas, pixels_computed, band * rows_per_band, pass, cancelled))
+                )
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .sum()
+        })
+    }
+}
+
+struct Pass<T: RealField + Copy> {
+    width: usize,
+    height: usize,
+    accuracy: usize,
+    // Pixels per screen unit, the same on both axes.
+    pixel_scale: T,
+    objects: Vec<Prepared<T>>,
+    background: (f64, f64, f64, f64),
+    lighting: Lighting,
+    light: Vector3<T>,
+    rays: Rays<T>,
+    incremental: bool,
+}
+
+impl<T: RealField + Copy> Pass<T> {
+    fn screen_x(&self, i: usize) -> T {
+        convert::<f64, T>((i as i32 - (self.width as i32 / 2)) as f64) / self.pixel_scale
+    }
+
+    fn screen_y(&self, j: usize) -> T {
+        -(convert::<f64, T>((j as i32 - (self.height as i32 / 2)) as f64) / self.pixel_scale)
+    }
+}
+
+// Forms are taken relative to the object's centre and rays start where the central ray passes
+// it, rather than at the camera. Both keep the coefficients of the ray equation small, so they
+// do not cancel when the object is far away.
+struct Prepared<T: RealField + Copy> {
+    d: Matrix4<T>,
+    // (d + d^T) / 2, which gives the same quadric and makes both cross terms of b equal.
+    symmetric: Matrix4<T>,
+    gradient: Matrix4<T>,
+    centre: Vector4<T>,
+    // Ray parameter at which the central ray passes the centre.
+    shift: T,
+    color: (f64, f64, f64),
+    // Pixels whose rays can meet the object at all; everything else skips it without solving.
+    columns: Range<usize>,
+    rows: Range<usize>,
+}
+
+impl<T: RealField + Copy> Prepared<T> {
+    fn covers(&self, i: usize, j: usize) -> bool {
+        self.columns.contains(&i) && self.rows.contains(&j)
+    }
+
+    // Where the ray equation for this object starts, relative to its centre.
+    fn start(&self, origin: &Vector4<T>, direction: &Vector4<T>) -> Vector4<T> {
+        origin - self.centre + direction * self.shift
+    }
+}
+
+// Coefficients a, b and c of the ray equation a t^2 + b t + c = 0 along one row, each as a
+// polynomial in x of degree two, lowest power first. They follow from expanding
+// (o + x do)^T s (o + x do) and its kin, where ray(x, y) = (o, e) + x (do, de) with the
+// origin taken from `Prepared::start`.
+struct Row<T: RealField + Copy> {
+    a: [T; 3],
+    b: [T; 3],
+    c: [T; 3],
+}
+
+impl<T: RealField + Copy> Row<T> {
+    fn new(object: &Prepared<T>, rays: &Rays<T>, y: T) -> Self {
+        let symmetric = &object.symmetric;
+        let (origin, direction) = rays.ray(T::zero(), y);
+        let (origin_step, direction_step) = rays.step();
+        let (origin, origin_step) = (object.start(&origin, &direction), origin_step + direction_step * object.shift);
+        let (s_origin, s_direction) = (symmetric * origin, symmetric * direction);
+        let (s_origin_step, s_direction_step) = (symmetric * origin_step, symmetric * direction_step);
+        let two = convert::<f64, T>(2.0);
+
+        Row {
+            a: [
+                direction.dot(&s_direction),
+                two * direction_step.dot(&s_direction),
+                direction_step.dot(&s_direction_step),
+            ],
+            b: [
+                two * origin.dot(&s_direction),
+                two * (origin_step.dot(&s_direction) + origin.dot(&s_direction_step)),
+                two * origin_step.dot(&s_direction_step),
+            ],
+            c: [
+                origin.dot(&s_origin),
+                two * origin_step.dot(&s_origin),
+                origin_step.dot(&s_origin_step),
+            ],
+        }
+    }
+
+    fn at(&self, x: T) -> (T, T, T) {
+        let horner = |p: &[T; 3]| (p[2] * x + p[1]) * x + p[0];
+        (horner(&self.a), horner(&self.b), horner(&self.c))
+    }
+}
+
+fn draw_band<T: RealField + Copy>(canvas: &mut [u8], pixels_computed: &mut [bool], first_row: usize, pass: &Pass<T>, cancelled: &(dyn Fn() -> bool + Sync)) -> Option<usize> {
+    let (width, accuracy) = (pass.width, pass.accuracy);
+    let rows = pixels_computed.len() / width;
+    let mut pixels_shaded = 0;
+    let mut row = Vec::with_capacity(pass.objects.len());
+
+    for j in first_row..first_row + rows {
+        if cancelled() {
+            return None;
+        }
+        // Without incremental evaluation, or for objects this row misses, every entry is None.
+        row.clear();
+        if pass.incremental && j % accuracy == 0 {
+            let y = pass.screen_y(j);
+            row.extend(pass.objects.iter().map(|object| object.rows.contains(&j).then(|| Row::new(object, &pass.rays, y))));
+        } else {
+            row.resize_with(pass.objects.len(), || None);
+        }
+
+        for i in 0..width {
+            let pixel_index = (j - first_row) * width + i;
+            if pixels_computed[pixel_index] {
+                continue;
+            }
+            let index = pixel_index * 4;
+            if i % accuracy != 0 || j % accuracy != 0 {
+                let colored_index = ((j / accuracy * accuracy - first_row) * width + i / accuracy * accuracy) * 4;
+                canvas.copy_within(colored_index..colored_index + 4, index);
+                continue;
+            }
+
+            (canvas[index], canvas[index + 1], canvas[index + 2], canvas[index + 3]) = shade(pass, i, j, &row);
+            pixels_computed[pixel_index] = true;
+            pixels_shaded += 1;
+        }
+    }
+    Some(pixels_shaded)
+}
+
+fn shade<T: RealField + Copy>(pass: &Pass<T>, i: usize, j: usize, row: &[Option<Row<T>>]) -> (u8, u8, u8, u8) {
+    let (r, g, b, a) = pass.background;
+    if !pass.objects.iter().any(|object| object.covers(i, j)) {
+        return to_rgba8((r, g, b), a);
+    }
+
+    let (x, y) = (pass.screen_x(i), pass.screen_y(j));
+    // Incremental evaluation only needs the ray itself at a hit.
+    let ray = || pass.rays.ray(x, y);
+
+    let nearest = pass.objects
+        .iter()
+        .zip(row)
+        .filter(|(object, _)| object.covers(i, j))
+        .filter_map(|(object, row)| {
+            let (a, b, c) = match row {
+                Some(row) => row.at(x),
+                None => {
+                    let (origin, direction) = ray();
+                    coefficients(&object.d, &object.start(&origin, &direction), &direction)
+                }
+            };
+            solve(a, b, c, object.shift, pass.rays.projection).map(|t| (t, object))
+        })
+        .fold(None, |nearest: Option<(T, &Prepared<T>)>, (t, object)| match nearest {
+            Some((nearest_t, _)) if nearest_t <= t => nearest,
+            _ => Some((t, object)),
+        });
+    let Some((t, object)) = nearest else {
+        return to_rgba8((r, g, b), a);
+    };
+
+    let (origin, direction) = ray();
+    // Relative to the centre, like the gradient.
+    let p = origin - object.centre + direction * t;
+    let mut n = (object.gradient * p).xyz().normalize();
+    let v = -direction.xyz().normalize();
+    // Open surfaces are seen from both sides, so light whichever side faces the viewer.
+    if n.dot(&v) < T::zero() {
+        n = -n;
+    }
+
+    let lighting = &pass.lighting;
+    let (diffuse, specular) = lighting.intensity(&pass.light, &n, &v);
+    let intensity = lighting.ambient + lighting.diffuse * diffuse;
+    let highlight = lighting.specular * specular;
+
+    to_rgba8((
+        object.color.0 * intensity + highlight,
+        object.color.1 * intensity + highlight,
+        object.color.2 * intensity + highlight,
+    ), 1.0)
+}
+
+// Screen rectangle, as x and y ranges, outside which no ray meets the quadric d. A ray hits
+// where b^2 - 4ac >= 0, and since either the origin or the direction is the same for every
+// ray, that discriminant is a quadratic form in (x, y, 1): a conic. None unless the conic is an
+// ellipse, as for an ellipsoid seen from outside or a cylinder seen end on.
+fn screen_bounds(d: &Matrix4<f64>, rays: &Rays<f64>) -> Option<((f64, f64), (f64, f64))> {
+    let d = (d + d.transpose()) / 2.0;
+    let (o0, e0) = rays.ray(0.0, 0.0);
+    let (ox, ex) = rays.ray(1.0, 0.0);
+    let (oy, ey) = rays.ray(0.0, 1.0);
+    // Origin and direction as linear maps of (x, y, 1).
+    let origin = Matrix4x3::from_columns(&[ox - o0, oy - o0, o0]);
+    let direction = Matrix4x3::from_columns(&[ex - e0, ey - e0, e0]);
+
+    let q: Matrix3<f64> = match rays.projection {
+        Projection::Orthographic => {
+            let b = origin.transpose() * d * e0 * 2.0;
+            b * b.transpose() - origin.transpose() * d * origin * (4.0 * e0.dot(&(d * e0)))
+        }
+        Projection::Perspective => {
+            let b = direction.transpose() * d * o0 * 2.0;
+            b * b.transpose() - direction.transpose() * d * direction * (4.0 * o0.dot(&(d * o0)))
+        }
+    };
+
+    // Inside the conic (p - centre)^T n (p - centre) <= k, with n positive definite.
+    let n = -Matrix2::new(q[(0, 0)], q[(0, 1)], q[(1, 0)], q[(1, 1)]);
+    let g = Vector2::new(q[(0, 2)], q[(1, 2)]);
+    if n[(0, 0)] <= 0.0 || n.determinant() <= 1e-9 * n[(0, 0)] * n[(1, 1)] {
+        return None;
+    }
+    let n_inverse = n.try_inverse()?;
+    let centre = n_inverse * g;
+    let k = (q[(2, 2)] + g.dot(&centre)).max(0.0);
+
+    let half_width = (k * n_inverse[(0, 0)]).sqrt();
+    let half_height = (k * n_inverse[(1, 1)]).sqrt();
+    Some((
+        (centre.x - half_width, centre.x + half_width),
+        (centre.y - half_height, centre.y + half_height),
+    ))
+}
+
+// Columns and rows of the pixels over a screen rectangle, one pixel wider on every side to
+// absorb rounding in the per-pixel solve.
+fn pixel_bounds(x: (f64, f64), y: (f64, f64), width: usize, height: usize, pixel_scale: f64) -> (Range<usize>, Range<usize>) {
+    let to_pixels = |low: f64, high: f64, centre: usize, size: usize| {
+        let first = (low * pixel_scale + centre as f64).floor() - 1.0;
+        let last = (high * pixel_scale + centre as f64).ceil() + 1.0;
+        first.clamp(0.0, size as f64) as usize..(last + 1.0).clamp(0.0, size as f64) as usize
+    };
+    // Rows count downwards from the top while y points up.
+    (to_pixels(x.0, x.1, width / 2, width), to_pixels(-y.1, -y.0, height / 2, height))
+}
+
+// Coefficients a, b and c of a t^2 + b t + c = 0 for the points origin + t * direction on the
+// quadric p^T d p = 0.
+fn coefficients<T: RealField + Copy>(d: &Matrix4<T>, origin: &Vector4<T>, direction: &Vector4<T>) -> (T, T, T) {
+    let d_origin = d * origin;
+    let a = direction.dot(&(d * direction));
+    let b = direction.dot(&d_origin) + origin.dot(&(d * direction));
+    let c = origin.dot(&d_origin);
+    (a, b, c)
+}
+
+// Nearest root of a t^2 + b t + c = 0 that the camera sees, plus `shift` for equations set up
+// that far along the ray. An orthographic camera sees the whole line, a perspective one only
+// what is in front of the eye.
+fn solve<T: RealField + Copy>(a: T, b: T, c: T, shift: T, projection: Projection) -> Option<T> {
+    let visible = |t: T| projection == Projection::Orthographic || t > T::zero();
+
+    let delta = b * b - convert::<f64, T>(4.0) * a * c;
+    if delta < T::zero() {
+        return None;
+    }
+
+    // q takes the sign of b so that adding the square root never cancels. The roots are then
+    // c / q and q / a, which also covers a = 0, where only the first one is left.
+    let q = -(b + delta.sqrt().copysign(b)) / convert(2.0);
+    if q == T::zero() {
+        return None;
+    }
+    let t1 = c / q + shift;
+    // When a is lost in the rounding of b, q / a is noise far behind everything else.
+    let t2 = if a.abs() > b.abs() * T::default_epsilon() { q / a + shift } else { t1 };
+    let (near, far) = if t1 <= t2 { (t1, t2) } else { (t2, t1) };
+
+    if visible(near) {
+        Some(near)
+    } else if visible(far) {
+        Some(far)
+    } else {
+        None
+    }
+}
+
+fn to_rgba8(color: (f64, f64, f64), alpha: f64) -> (u8, u8, u8, u8) {
+    (
+        (color.0.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (color.1.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (color.2.clamp(0.0, 1.0) * 255.0).round() as u8,
+        (alpha.clamp(0.0, 1.0) * 255.0).round() as u8,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::{samples, Object};
+
+    const WIDTH: usize = 96;
+    const HEIGHT: usize = 61;
+
+    // The shared samples, plus one sphere in front of a perspective eye and one behind it.
+    fn scenes() -> Vec<Scene> {
+        let mut behind = Scene::new();
+        behind.camera.projection = Projection::Perspective;
+        behind.objects = vec![
+            Object { scale: (0.5, 0.5, 0.5), translation: (0.3, 0.0, 0.0), ..Object::new() },
+            Object { scale: (0.5, 0.5, 0.5), translation: (0.0, 0.0, 5.0), ..Object::new() },
+        ];
+
+        samples().into_iter().map(|(_, scene)| scene).chain([behind]).collect()
+    }
+
+    fn draw<T: RealField + Copy>(pass: &Pass<T>) -> Vec<u8> {
+        let mut canvas = vec![0; pass.width * pass.height * 4];
+        let mut pixels_computed = vec![false; pass.width * pass.height];
+        draw_band(&mut canvas, &mut pixels_computed, 0, pass, &|| false);
+        canvas
+    }
+
+    fn assert_close(actual: Option<f64>, expected: Option<f64>) {
+        match (actual, expected) {
+            (Some(actual), Some(expected)) => assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected),
+            _ => assert_eq!(actual, expected),
+        }
+    }
+
+    #[test]
+    fn solve_finds_the_nearest_visible_root() {
+        // (t - 1)(t - 3)
+        assert_close(solve(1.0, -4.0, 3.0, 0.0, Projection::Orthographic), Some(1.0));
+        assert_close(solve(1.0, -4.0, 3.0, 0.0, Projection::Perspective), Some(1.0));
+        // t^2 + 1 has no real roots.
+        assert_close(solve(1.0, 0.0, 1.0, 0.0, Projection::Orthographic), None);
+    }
+
+    #[test]
+    fn solve_handles_a_vanishing_quadratic_term() {
+        // 2t - 4
+        assert_close(solve(0.0, 2.0, -4.0, 0.0, Projection::Orthographic), Some(2.0));
+        assert_close(solve(1e-30, 2.0, -4.0, 0.0, Projection::Perspective), Some(2.0));
+        assert_close(solve(0.0, 0.0, 1.0, 0.0, Projection::Orthographic), None);
+    }
+
+    #[test]
+    fn solve_touches_tangent_rays_once() {
+        // (t - 2)^2
+        assert_close(solve(1.0, -4.0, 4.0, 0.0, Projection::Perspective), Some(2.0));
+        assert_close(solve(1.0, 4.0, 4.0, 0.0, Projection::Orthographic), Some(-2.0));
+        assert_close(solve(1.0, 4.0, 4.0, 0.0, Projection::Perspective), None);
+    }
+
+    #[test]
+    fn solve_ignores_roots_behind_a_perspective_eye() {
+        // (t + 1)(t + 3), entirely behind the eye
+        assert_close(solve(1.0, 4.0, 3.0, 0.0, Projection::Perspective), None);
+        assert_close(solve(1.0, 4.0, 3.0, 0.0, Projection::Orthographic), Some(-3.0));
+        // (t + 1)(t - 2), the eye is inside
+        assert_close(solve(1.0, -1.0, -2.0, 0.0, Projection::Perspective), Some(2.0));
+        assert_close(solve(1.0, -1.0, -2.0, 0.0, Projection::Orthographic), Some(-1.0));
+    }
+
+    #[test]
+    fn solve_measures_roots_from_the_shift() {
+        // (t + 1)(t + 3) set up 4 along the ray, so in front of the eye after all
+        assert_close(solve(1.0, 4.0, 3.0, 4.0, Projection::Perspective), Some(1.0));
+        // (t + 1)(t - 2) set up 3 behind the eye
+        assert_close(solve(1.0, -1.0, -2.0, -3.0, Projection::Perspective), None);
+        assert_close(solve(1.0, -1.0, -2.0, -3.0, Projection::Orthographic), Some(-4.0));
+    }
+
+    #[test]
+    fn screen_bounds_leave_the_image_unchanged() {
+        let mut renderer = Renderer::new();
+        for scene in scenes() {
+            renderer.update(&scene, WIDTH, HEIGHT);
+            for incremental in [false, true] {
+                let mut pass = renderer.pass::<f64>();
+                pass.incremental = incremental;
+                let bounded = draw(&pass);
+                for object in &mut pass.objects {
+                    (object.columns, object.rows) = (0..WIDTH, 0..HEIGHT);
+                }
+                assert!(bounded == draw(&pass));
+            }
+        }
+    }
+
+    #[test]
+    fn screen_bounds_fit_a_sphere() {
+        let mut renderer = Renderer::new();
+        let mut scene = Scene::new();
+        scene.objects[0].scale = (0.5, 0.5, 0.5);
+        renderer.update(&scene, WIDTH, HEIGHT);
+        let pass = renderer.pass::<f64>();
+
+        // Radius 0.5 at 30 pixels per unit around the centre pixel (48, 30), padded by a pixel.
+        let object = &pass.objects[0];
+        assert_eq!((object.columns.clone(), object.rows.clone()), (32..65, 14..47));
+    }
+
+    #[test]
+    fn incremental_evaluation_stays_within_tolerance() {
+        let mut renderer = Renderer::new();
+        for precision in Precision::ALL {
+            renderer.set_precision(precision);
+            for (name, scene) in samples() {
+                renderer.set_incremental(false);
+                let reference = renderer.render(&scene, WIDTH * 2, HEIGHT * 2).to_vec();
+                renderer.set_incremental(true);
+                let image = renderer.render(&scene, WIDTH * 2, HEIGHT * 2);
+
+                let differing = reference
+                    .chunks(4)
+                    .zip(image.chunks(4))
+                    .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > 1))
+                    .count();
+                let fraction = differing as f64 / (WIDTH * HEIGHT * 4) as f64;
+                assert!(fraction <= INCREMENTAL_TOLERANCE, "{}, {}: {} pixels differ", name, precision.key(), differing);
+            }
+        }
+    }
+
+    #[test]
+    fn output_does_not_depend_on_the_thread_count() {
+        let mut renderer = Renderer::new();
+        for precision in Precision::ALL {
+            renderer.set_precision(precision);
+            for scene in scenes() {
+                renderer.set_threads(1);
+                let reference = renderer.render(&scene, WIDTH, HEIGHT).to_vec();
+                for threads in [2, 3, 7, HEIGHT + 1] {
+                    renderer.set_threads(threads);
+                    assert!(renderer.render(&scene, WIDTH, HEIGHT) == reference, "{} threads", threads);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coarse_passes_do_not_depend_on_the_thread_count() {
+        for scene in scenes() {
+            let draw = |threads| {
+                let mut renderer = Renderer::new();
+                renderer.set_threads(threads);
+                renderer.draw(&scene, WIDTH, HEIGHT);
+                renderer.buffer().to_vec()
+            };
+            let reference = draw(1);
+            for threads in [2, 3, 7] {
+                assert!(draw(threads) == reference, "{} threads", threads);
+            }
+        }
+    }
+
+    // The worker only hands the canvas a new image when a draw wrote the buffer.
+    #[test]
+    fn drawing_the_same_frame_again_changes_nothing() {
+        let (_, scene) = &samples()[0];
+        let mut renderer = Renderer::new();
+        renderer.set_accuracy(4);
+        renderer.draw(scene, WIDTH, HEIGHT);
+        assert!(renderer.redrawn());
+        renderer.draw(scene, WIDTH, HEIGHT);
+        assert!(!renderer.redrawn());
+
+        renderer.set_accuracy(1);
+        renderer.draw(scene, WIDTH, HEIGHT);
+        assert!(renderer.redrawn());
+        renderer.draw(scene, WIDTH + 1, HEIGHT);
+        assert!(renderer.redrawn());
+    }
+}
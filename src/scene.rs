@@ -0,0 +1,211 @@
+use nalgebra::{convert, Matrix4, RealField, UnitQuaternion, Vector3, Vector4};
+use crate::camera::{Camera, Projection};
+use crate::lighting::Lighting;
+use crate::surface::Surface;
+
+// Colours handed out to new objects in turn, starting with yellow.
+pub const PALETTE: [(f64, f64, f64); 6] = [
+    (1.0, 1.0, 0.0),
+    (0.0, 1.0, 1.0),
+    (1.0, 0.0, 1.0),
+    (1.0, 0.5, 0.0),
+    (0.5, 1.0, 0.0),
+    (0.3, 0.5, 1.0),
+];
+
+// Scale components closer to zero than this flatten an object into something that can't be drawn.
+pub const MIN_SCALE: f64 = 1e-3;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Scene {
+    pub objects: Vec<Object>,
+    pub camera: Camera,
+    pub lighting: Lighting,
+    // RGBA, an alpha below 1 lets exported images be composited over other content.
+    pub background: (f64, f64, f64, f64),
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene {
+            objects: vec![Object::new()],
+            camera: Camera::new(),
+            lighting: Lighting::new(),
+            background: (0.0, 0.0, 0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Object {
+    pub surface: Surface,
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub scale: (f64, f64, f64),
+    // Negative scale components turn the object inside out, so they are only accepted when set.
+    pub mirror: bool,
+    pub rotation: UnitQuaternion<f64>,
+    pub translation: (f64, f64, f64),
+    pub color: (f64, f64, f64),
+}
+
+impl Default for Object {
+    fn default() -> Self {
+        Object::new()
+    }
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Object {
+            surface: Surface::Ellipsoid,
+            a: 1.0,
+            b: 1.0,
+            c: 1.0,
+            scale: (1.0, 1.0, 1.0),
+            mirror: false,
+            rotation: UnitQuaternion::identity(),
+            translation: (0.0, 0.0, 0.0),
+            color: PALETTE[0],
+        }
+    }
+
+    // Why the object can't be drawn as described, if it can't.
+    pub fn validate(&self) -> Result<(), String> {
+        let sizes = [("a", self.a), ("b", self.b), ("c", self.c)];
+        let used = if self.surface.uses_c() { 3 } else { 2 };
+        for (name, size) in &sizes[..used] {
+            if !(size.is_finite() && *size > 0.0) {
+                return Err(format!("{} must be positive, got {}", name, size));
+            }
+        }
+
+        let scales = [("x", self.scale.0), ("y", self.scale.1), ("z", self.scale.2)];
+        for (axis, scale) in scales {
+            if !(scale.is_finite() && scale.abs() >= MIN_SCALE) {
+                return Err(format!("scale {} must be at least {} away from zero, got {}", axis, MIN_SCALE, scale));
+            }
+            if scale < 0.0 && !self.mirror {
+                return Err(format!("scale {} is negative, which needs mirroring enabled", axis));
+            }
+        }
+        Ok(())
+    }
+
+    // The quadric in world space, worked out in the precision T that the renderer traces in.
+    // None when the object fails `validate` or its transform can't be inverted.
+    pub fn get_d<T: RealField + Copy>(&self) -> Option<Matrix4<T>> {
+        self.validate().ok()?;
+        let d = self.surface.form(self.a, self.b, self.c).map(convert::<f64, T>);
+        let (x, y, z) = self.scale;
+        let m = self.get_translation_matrix::<T>()
+            * self.get_rotation_matrix()
+            * Matrix4::from_diagonal(&Vector4::new(x, y, z, 1.0).map(convert));
+        let mi = m.try_inverse()?;
+        Some(mi.transpose() * d * mi)
+    }
+
+    pub fn get_rotation_matrix<T: RealField + Copy>(&self) -> Matrix4<T> {
+        self.rotation.to_homogeneous().map(convert)
+    }
+
+    pub fn get_translation_matrix<T: RealField + Copy>(&self) -> Matrix4<T> {
+        let (x, y, z) = self.translation;
+        Matrix4::new_translation(&Vector3::new(x, y, z).map(convert))
+    }
+}
+
+
+// The rotation Rx(x) * Ry(y) * Rz(z), angles in radians.
+pub fn euler_to_quaternion((x, y, z): (f64, f64, f64)) -> UnitQuaternion<f64> {
+    // nalgebra composes its angles the other way round, as Rz * Ry * Rx.
+    UnitQuaternion::from_euler_angles(-x, -y, -z).inverse()
+}
+
+// Angles (x, y, z) such that `euler_to_quaternion` gives back the same rotation, with y in -pi/2..pi/2.
+pub fn quaternion_to_euler(rotation: &UnitQuaternion<f64>) -> (f64, f64, f64) {
+    let (x, y, z) = rotation.inverse().euler_angles();
+    (-x, -y, -z)
+}
+
+// A few named scenes covering closed and open surfaces, both projections and an object far
+// from the camera, shared by the renderer tests and the benchmark binary.
+pub fn samples() -> Vec<(&'static str, Scene)> {
+    let object = |surface, scale: f64, rotation, translation, index: usize| Object {
+        surface,
+        scale: (scale, scale, scale),
+        rotation: euler_to_quaternion(rotation),
+        translation,
+        color: PALETTE[index % PALETTE.len()],
+        ..Object::new()
+    };
+
+    let sphere = Scene::new();
+
+    let mut flat = Scene::new();
+    flat.objects[0] = Object { a: 1.0, b: 0.8, c: 0.1, ..object(Surface::Ellipsoid, 1.0, (1.1, 0.4, 0.2), (0.0, 0.0, 0.0), 0) };
+
+    // Far from the camera, where the ray equation is prone to cancellation.
+    let mut far = flat.clone();
+    far.objects[0].translation = (0.0, 0.0, 50.0);
+
+    let mut mixed = Scene::new();
+    mixed.objects = vec![
+        object(Surface::Ellipsoid, 0.6, (0.3, 0.5, 0.2), (-0.8, 0.3, 0.0), 0),
+        object(Surface::HyperboloidOfOneSheet, 0.3, (1.0, -0.4, 2.5), (0.7, -0.2, -1.0), 1),
+        object(Surface::EllipticCylinder, 0.2, (0.2, 0.9, 0.0), (0.0, -0.6, 0.5), 2),
+    ];
+
+    let mut perspective = mixed.clone();
+    perspective.camera.projection = Projection::Perspective;
+    perspective.camera.eye = (2.0, 1.5, 3.0);
+
+    vec![
+        ("sphere", sphere),
+        ("flat ellipsoid", flat),
+        ("flat ellipsoid, far away", far),
+        ("three surfaces", mixed),
+        ("three surfaces, perspective", perspective),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Rotation3;
+
+    const ANGLES: [(f64, f64, f64); 5] = [
+        (0.0, 0.0, 0.0),
+        (0.3, 0.0, 0.0),
+        (0.0, -0.7, 0.0),
+        (0.0, 0.0, 2.5),
+        (1.1, 0.4, -2.9),
+    ];
+
+    #[test]
+    fn euler_angles_rotate_about_x_then_y_then_z() {
+        for (x, y, z) in ANGLES {
+            let explicit = Rotation3::from_axis_angle(&Vector3::x_axis(), x)
+                * Rotation3::from_axis_angle(&Vector3::y_axis(), y)
+                * Rotation3::from_axis_angle(&Vector3::z_axis(), z);
+            let rotation = euler_to_quaternion((x, y, z)).to_rotation_matrix();
+            assert!((rotation.matrix() - explicit.matrix()).amax() < 1e-12, "{:?}", (x, y, z));
+        }
+    }
+
+    #[test]
+    fn euler_angles_survive_a_round_trip() {
+        for angles in ANGLES {
+            let (x, y, z) = quaternion_to_euler(&euler_to_quaternion(angles));
+            let error = (x - angles.0).abs().max((y - angles.1).abs()).max((z - angles.2).abs());
+            assert!(error < 1e-12, "{:?} came back as {:?}", angles, (x, y, z));
+        }
+    }
+}
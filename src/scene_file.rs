@@ -0,0 +1,429 @@
+//! Scene files are TOML documents describing everything needed to reproduce a render.
+//!
+//! ```toml
+//! version = 1
+//! background = [0.0, 0.0, 0.0, 1.0]  # RGBA, components in 0..1
+//!
+//! [camera]
+//! projection = "orthographic"        # or "perspective"
+//! eye = [0.0, 0.0, 3.0]
+//! target = [0.0, 0.0, 0.0]
+//! fov = 45.0                         # degrees across the fitted side, below 180, perspective only
+//! fit = "shorter"                    # side spanning -1..1: "shorter", "width" or "height"
+//!
+//! [lighting]
+//! reflection = "phong"               # or "blinn-phong"
+//! ambient = 0.1
+//! diffuse = 0.7
+//! specular = 0.3
+//! shininess = 20.0
+//! light_direction = [-1.0, 1.0, 1.0]
+//!
+//! [render]
+//! threads = 0                        # 0 uses every core
+//! precision = "single"               # or "double", slower but steadier on extreme shapes
+//! min_accuracy = 32                  # coarsest progressive step, in pixels
+//! max_timer = 0.2                    # seconds without changes before each refinement step
+//! timer_step = 0.1                   # seconds between checks for the next step
+//! full_accuracy = false              # skip progressive refinement altogether
+//! adaptive_refinement = true         # pick the accuracy from measured frame times
+//! target_frame_time = 33.0           # milliseconds per frame while the scene changes
+//!
+//! [[objects]]
+//! surface = "ellipsoid"              # see `Surface::key`
+//! a = 1.0
+//! b = 1.0
+//! c = 1.0
+//! scale = [1.0, 1.0, 1.0]           # no component may be zero
+//! mirror = false                     # allow negative scale components
+//! rotation = [0.0, 0.0, 0.0]         # radians, applied as Rx * Ry * Rz
+//! translation = [0.0, 0.0, 0.0]
+//! color = [1.0, 1.0, 0.0]
+//! ```
+//!
+//! Every key except `version` is optional and falls back to the defaults above, so files
+//! written by older versions keep loading. `version` is bumped whenever a key changes meaning.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml::{Table, Value};
+use crate::camera::{Camera, Fit, Projection};
+use crate::lighting::Reflection;
+use crate::renderer::Precision;
+use crate::scene::{euler_to_quaternion, quaternion_to_euler, Object, Scene};
+use crate::surface::Surface;
+
+pub const VERSION: i64 = 1;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SceneFile {
+    pub scene: Scene,
+    pub threads: usize,
+    pub precision: Precision,
+    pub min_accuracy: usize,
+    pub max_timer: f64,
+    pub timer_step: f64,
+    pub full_accuracy: bool,
+    pub adaptive_refinement: bool,
+    pub target_frame_time: f64,
+}
+
+impl Default for SceneFile {
+    fn default() -> Self {
+        SceneFile::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Syntax(toml::de::Error),
+    UnsupportedVersion(i64),
+    Invalid(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{}", error),
+            Error::Syntax(error) => write!(f, "{}", error),
+            Error::UnsupportedVersion(version) => write!(f, "unsupported scene file version {} (expected {})", version, VERSION),
+            Error::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl SceneFile {
+    pub fn new() -> Self {
+        SceneFile {
+            scene: Scene::new(),
+            threads: 0,
+            precision: Precision::Single,
+            min_accuracy: 32,
+            max_timer: 0.2,
+            timer_step: 0.1,
+            full_accuracy: false,
+            adaptive_refinement: true,
+            target_frame_time: 33.0,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = fs::read_to_string(path).map_err(Error::Io)?;
+        SceneFile::parse(&text)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_toml())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let table = text.parse::<Table>().map_err(Error::Syntax)?;
+
+        let version = table
+            .get("version")
+            .ok_or_else(|| Error::Invalid("missing version".to_string()))?
+            .as_integer()
+            .ok_or_else(|| Error::Invalid("version must be an integer".to_string()))?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut file = SceneFile::new();
+        let scene = &mut file.scene;
+        scene.background = read_quadruple(&table, "background", scene.background)?;
+
+        if let Some(camera) = read_table(&table, "camera")? {
+            let projection = read_str(camera, "projection", "orthographic")?;
+            scene.camera.projection = match projection {
+                "orthographic" => Projection::Orthographic,
+                "perspective" => Projection::Perspective,
+                _ => return Err(Error::Invalid(format!("unknown projection {}", projection))),
+            };
+            scene.camera.eye = read_triple(camera, "eye", scene.camera.eye)?;
+            scene.camera.target = read_triple(camera, "target", scene.camera.target)?;
+            scene.camera.fov = read_float(camera, "fov", scene.camera.fov)?;
+            if !Camera::fov_allowed(scene.camera.fov) {
+                return Err(Error::Invalid(format!("fov must be between 0 and 180 degrees, got {}", scene.camera.fov)));
+            }
+            let fit = read_str(camera, "fit", scene.camera.fit.key())?;
+            scene.camera.fit = Fit::from_key(fit).ok_or_else(|| Error::Invalid(format!("unknown fit mode {}", fit)))?;
+        }
+
+        if let Some(lighting) = read_table(&table, "lighting")? {
+            let reflection = read_str(lighting, "reflection", scene.lighting.reflection.key())?;
+            scene.lighting.reflection = Reflection::from_key(reflection)
+                .ok_or_else(|| Error::Invalid(format!("unknown reflection model {}", reflection)))?;
+            scene.lighting.ambient = read_float(lighting, "ambient", scene.lighting.ambient)?;
+            scene.lighting.diffuse = read_float(lighting, "diffuse", scene.lighting.diffuse)?;
+            scene.lighting.specular = read_float(lighting, "specular", scene.lighting.specular)?;
+            scene.lighting.shininess = read_float(lighting, "shininess", scene.lighting.shininess)?;
+            scene.lighting.light_direction = read_triple(lighting, "light_direction", scene.lighting.light_direction)?;
+        }
+
+        if let Some(render) = read_table(&table, "render")? {
+            file.threads = read_count(render, "threads", file.threads)?;
+            let precision = read_str(render, "precision", file.precision.key())?;
+            file.precision = Precision::from_key(precision)
+                .ok_or_else(|| Error::Invalid(format!("unknown precision {}", precision)))?;
+            file.min_accuracy = read_count(render, "min_accuracy", file.min_accuracy)?.max(1);
+            file.max_timer = read_float(render, "max_timer", file.max_timer)?.max(0.0);
+            file.timer_step = read_float(render, "timer_step", file.timer_step)?;
+            if file.timer_step <= 0.0 {
+                return Err(Error::Invalid("timer_step must be positive".to_string()));
+            }
+            file.full_accuracy = read_bool(render, "full_accuracy", file.full_accuracy)?;
+            file.adaptive_refinement = read_bool(render, "adaptive_refinement", file.adaptive_refinement)?;
+            file.target_frame_time = read_float(render, "target_frame_time", file.target_frame_time)?.max(1.0);
+        }
+
+        if let Some(objects) = table.get("objects") {
+            let objects = objects
+                .as_array()
+                .ok_or_else(|| Error::Invalid("objects must be an array of tables".to_string()))?;
+            file.scene.objects = objects
+                .iter()
+                .enumerate()
+                .map(|(index, object)| read_object(object).map_err(|error| Error::Invalid(format!("object {}: {}", index + 1, error))))
+                .collect::<Result<_, _>>()?;
+        }
+        if file.scene.objects.is_empty() {
+            return Err(Error::Invalid("a scene needs at least one object".to_string()));
+        }
+
+        Ok(file)
+    }
+
+    pub fn to_toml(&self) -> String {
+        let scene = &self.scene;
+        let mut table = Table::new();
+        table.insert("version".to_string(), Value::Integer(VERSION));
+        table.insert("background".to_string(), quadruple(scene.background));
+
+        let mut camera = Table::new();
+        let projection = match scene.camera.projection {
+            Projection::Orthographic => "orthographic",
+            Projection::Perspective => "perspective",
+        };
+        camera.insert("projection".to_string(), Value::from(projection));
+        camera.insert("eye".to_string(), triple(scene.camera.eye));
+        camera.insert("target".to_string(), triple(scene.camera.target));
+        camera.insert("fov".to_string(), Value::Float(scene.camera.fov));
+        camera.insert("fit".to_string(), Value::from(scene.camera.fit.key()));
+        table.insert("camera".to_string(), Value::Table(camera));
+
+        let mut lighting = Table::new();
+        lighting.insert("reflection".to_string(), Value::from(scene.lighting.reflection.key()));
+        lighting.insert("ambient".to_string(), Value::Float(scene.lighting.ambient));
+        lighting.insert("diffuse".to_string(), Value::Float(scene.lighting.diffuse));
+        lighting.insert("specular".to_string(), Value::Float(scene.lighting.specular));
+        lighting.insert("shininess".to_string(), Value::Float(scene.lighting.shininess));
+        lighting.insert("light_direction".to_string(), triple(scene.lighting.light_direction));
+        table.insert("lighting".to_string(), Value::Table(lighting));
+
+        let mut render = Table::new();
+        render.insert("threads".to_string(), Value::Integer(self.threads as i64));
+        render.insert("precision".to_string(), Value::from(self.precision.key()));
+        render.insert("min_accuracy".to_string(), Value::Integer(self.min_accuracy as i64));
+        render.insert("max_timer".to_string(), Value::Float(self.max_timer));
+        render.insert("timer_step".to_string(), Value::Float(self.timer_step));
+        render.insert("full_accuracy".to_string(), Value::Boolean(self.full_accuracy));
+        render.insert("adaptive_refinement".to_string(), Value::Boolean(self.adaptive_refinement));
+        render.insert("target_frame_time".to_string(), Value::Float(self.target_frame_time));
+        table.insert("render".to_string(), Value::Table(render));
+
+        let objects = scene.objects.iter().map(write_object).collect();
+        table.insert("objects".to_string(), Value::Array(objects));
+
+        table.to_string()
+    }
+}
+
+fn read_object(value: &Value) -> Result<Object, Error> {
+    let table = value
+        .as_table()
+        .ok_or_else(|| Error::Invalid("objects must be an array of tables".to_string()))?;
+    let mut object = Object::new();
+
+    let surface = read_str(table, "surface", object.surface.key())?;
+    object.surface = Surface::from_key(surface).ok_or_else(|| Error::Invalid(format!("unknown surface {}", surface)))?;
+    object.a = read_float(table, "a", object.a)?;
+    object.b = read_float(table, "b", object.b)?;
+    object.c = read_float(table, "c", object.c)?;
+    object.scale = read_triple(table, "scale", object.scale)?;
+    object.mirror = read_bool(table, "mirror", object.mirror)?;
+    object.rotation = euler_to_quaternion(read_triple(table, "rotation", quaternion_to_euler(&object.rotation))?);
+    object.translation = read_triple(table, "translation", object.translation)?;
+    object.color = read_triple(table, "color", object.color)?;
+    object.validate().map_err(Error::Invalid)?;
+
+    Ok(object)
+}
+
+fn write_object(object: &Object) -> Value {
+    let mut table = Table::new();
+    table.insert("surface".to_string(), Value::from(object.surface.key()));
+    table.insert("a".to_string(), Value::Float(object.a));
+    table.insert("b".to_string(), Value::Float(object.b));
+    table.insert("c".to_string(), Value::Float(object.c));
+    table.insert("scale".to_string(), triple(object.scale));
+    table.insert("mirror".to_string(), Value::Boolean(object.mirror));
+    table.insert("rotation".to_string(), triple(quaternion_to_euler(&object.rotation)));
+    table.insert("translation".to_string(), triple(object.translation));
+    table.insert("color".to_string(), triple(object.color));
+    Value::Table(table)
+}
+
+fn read_table<'a>(table: &'a Table, key: &str) -> Result<Option<&'a Table>, Error> {
+    table
+        .get(key)
+        .map(|value| value.as_table().ok_or_else(|| Error::Invalid(format!("{} must be a table", key))))
+        .transpose()
+}
+
+fn read_str<'a>(table: &'a Table, key: &str, default: &'a str) -> Result<&'a str, Error> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(value) => value.as_str().ok_or_else(|| Error::Invalid(format!("{} must be a string", key))),
+    }
+}
+
+// Integers are accepted wherever a float is expected, so `a = 1` works as well as `a = 1.0`.
+fn as_float(value: &Value) -> Option<f64> {
+    value.as_float().or_else(|| value.as_integer().map(|value| value as f64))
+}
+
+fn read_float(table: &Table, key: &str, default: f64) -> Result<f64, Error> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(value) => as_float(value).ok_or_else(|| Error::Invalid(format!("{} must be a number", key))),
+    }
+}
+
+fn read_bool(table: &Table, key: &str, default: bool) -> Result<bool, Error> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(value) => value.as_bool().ok_or_else(|| Error::Invalid(format!("{} must be true or false", key))),
+    }
+}
+
+fn read_count(table: &Table, key: &str, default: usize) -> Result<usize, Error> {
+    match table.get(key) {
+        None => Ok(default),
+        Some(value) => value
+            .as_integer()
+            .and_then(|value| usize::try_from(value).ok())
+            .ok_or_else(|| Error::Invalid(format!("{} must be a non-negative integer", key))),
+    }
+}
+
+fn read_floats<const N: usize>(table: &Table, key: &str) -> Result<Option<[f64; N]>, Error> {
+    let Some(value) = table.get(key) else {
+        return Ok(None);
+    };
+    let invalid = || Error::Invalid(format!("{} must be an array of {} numbers", key, N));
+
+    let values = value
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(as_float)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(invalid)?;
+    values.try_into().map(Some).map_err(|_| invalid())
+}
+
+fn read_triple(table: &Table, key: &str, default: (f64, f64, f64)) -> Result<(f64, f64, f64), Error> {
+    Ok(read_floats(table, key)?.map_or(default, |[x, y, z]| (x, y, z)))
+}
+
+fn read_quadruple(table: &Table, key: &str, default: (f64, f64, f64, f64)) -> Result<(f64, f64, f64, f64), Error> {
+    Ok(read_floats(table, key)?.map_or(default, |[x, y, z, w]| (x, y, z, w)))
+}
+
+fn triple(value: (f64, f64, f64)) -> Value {
+    Value::Array(vec![Value::Float(value.0), Value::Float(value.1), Value::Float(value.2)])
+}
+
+fn quadruple(value: (f64, f64, f64, f64)) -> Value {
+    Value::Array(vec![Value::Float(value.0), Value::Float(value.1), Value::Float(value.2), Value::Float(value.3)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::PALETTE;
+
+    fn file() -> SceneFile {
+        let mut file = SceneFile::new();
+        file.threads = 3;
+        file.precision = Precision::Double;
+        file.min_accuracy = 16;
+        file.max_timer = 0.5;
+        file.full_accuracy = true;
+        file.adaptive_refinement = false;
+        file.target_frame_time = 50.0;
+
+        let scene = &mut file.scene;
+        scene.background = (0.1, 0.2, 0.3, 0.5);
+        scene.camera.projection = Projection::Perspective;
+        scene.camera.eye = (1.0, 2.0, 3.5);
+        scene.camera.fov = 60.0;
+        scene.camera.fit = Fit::Height;
+        scene.lighting.reflection = Reflection::BlinnPhong;
+        scene.lighting.shininess = 42.0;
+        scene.objects.push(Object {
+            surface: Surface::HyperboloidOfTwoSheets,
+            a: 0.5,
+            c: 2.0,
+            scale: (1.5, -0.25, 2.0),
+            mirror: true,
+            rotation: euler_to_quaternion((0.1, -0.7, 1.2)),
+            translation: (0.5, -1.0, 0.25),
+            color: PALETTE[3],
+            ..Object::new()
+        });
+        file
+    }
+
+    #[test]
+    fn round_trip() {
+        let file = file();
+        let parsed = SceneFile::parse(&file.to_toml()).unwrap();
+
+        // Rotations pass through Euler angles, so they only come back up to rounding.
+        for (object, parsed) in file.scene.objects.iter().zip(&parsed.scene.objects) {
+            assert!(object.rotation.angle_to(&parsed.rotation) < 1e-12);
+        }
+        let mut expected = file;
+        for (object, parsed) in expected.scene.objects.iter_mut().zip(&parsed.scene.objects) {
+            object.rotation = parsed.rotation;
+        }
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn missing_keys_take_defaults() {
+        assert_eq!(SceneFile::parse("version = 1").unwrap(), SceneFile::new());
+    }
+
+    #[test]
+    fn invalid_files_are_refused() {
+        assert!(matches!(SceneFile::parse("version = 2"), Err(Error::UnsupportedVersion(2))));
+        assert!(matches!(SceneFile::parse("[[objects]]"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\nobjects = []"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[[objects]]\nsurface = \"torus\""), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[render]\ntimer_step = 0"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[camera]\nfit = \"diagonal\""), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[camera]\nfov = 0.0"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[camera]\nfov = 180.0"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[render]\nprecision = \"half\""), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[[objects]]\nscale = [1, -1, 1]"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = 1\n[[objects]]\na = 0"), Err(Error::Invalid(_))));
+        assert!(matches!(SceneFile::parse("version = "), Err(Error::Syntax(_))));
+    }
+}
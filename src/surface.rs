@@ -0,0 +1,139 @@
+use nalgebra::{Matrix4, Vector4};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Surface {
+    Ellipsoid,
+    HyperboloidOfOneSheet,
+    HyperboloidOfTwoSheets,
+    EllipticParaboloid,
+    HyperbolicParaboloid,
+    EllipticCone,
+    EllipticCylinder,
+}
+
+impl Surface {
+    pub const ALL: [Surface; 7] = [
+        Surface::Ellipsoid,
+        Surface::HyperboloidOfOneSheet,
+        Surface::HyperboloidOfTwoSheets,
+        Surface::EllipticParaboloid,
+        Surface::HyperbolicParaboloid,
+        Surface::EllipticCone,
+        Surface::EllipticCylinder,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Surface::Ellipsoid => "Ellipsoid",
+            Surface::HyperboloidOfOneSheet => "Hyperboloid of one sheet",
+            Surface::HyperboloidOfTwoSheets => "Hyperboloid of two sheets",
+            Surface::EllipticParaboloid => "Elliptic paraboloid",
+            Surface::HyperbolicParaboloid => "Hyperbolic paraboloid",
+            Surface::EllipticCone => "Elliptic cone",
+            Surface::EllipticCylinder => "Elliptic cylinder",
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Surface::Ellipsoid => "ellipsoid",
+            Surface::HyperboloidOfOneSheet => "hyperboloid-one-sheet",
+            Surface::HyperboloidOfTwoSheets => "hyperboloid-two-sheets",
+            Surface::EllipticParaboloid => "elliptic-paraboloid",
+            Surface::HyperbolicParaboloid => "hyperbolic-paraboloid",
+            Surface::EllipticCone => "elliptic-cone",
+            Surface::EllipticCylinder => "elliptic-cylinder",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Surface::ALL.into_iter().find(|surface| surface.key() == key)
+    }
+
+    pub fn equation(&self) -> &'static str {
+        match self {
+            Surface::Ellipsoid => "x²/a² + y²/b² + z²/c² = 1",
+            Surface::HyperboloidOfOneSheet => "x²/a² + y²/b² - z²/c² = 1",
+            Surface::HyperboloidOfTwoSheets => "z²/c² - x²/a² - y²/b² = 1",
+            Surface::EllipticParaboloid => "x²/a² + y²/b² = z/c",
+            Surface::HyperbolicParaboloid => "x²/a² - y²/b² = z/c",
+            Surface::EllipticCone => "x²/a² + y²/b² = z²/c²",
+            Surface::EllipticCylinder => "x²/a² + y²/b² = 1",
+        }
+    }
+
+    pub fn uses_c(&self) -> bool {
+        *self != Surface::EllipticCylinder
+    }
+
+    // Symmetric matrix D of the surface in its own frame, so that p^T D p = 0 for p = (x, y, z, 1).
+    pub fn form(&self, a: f64, b: f64, c: f64) -> Matrix4<f64> {
+        let a = 1.0 / a / a;
+        let b = 1.0 / b / b;
+        let c2 = 1.0 / c / c;
+        let half_c = 0.5 / c;
+
+        match self {
+            Surface::Ellipsoid => Matrix4::from_diagonal(&Vector4::new(a, b, c2, -1.0)),
+            Surface::HyperboloidOfOneSheet => Matrix4::from_diagonal(&Vector4::new(a, b, -c2, -1.0)),
+            Surface::HyperboloidOfTwoSheets => Matrix4::from_diagonal(&Vector4::new(-a, -b, c2, -1.0)),
+            Surface::EllipticParaboloid => Matrix4::new(
+                a, 0.0, 0.0, 0.0,
+                0.0, b, 0.0, 0.0,
+                0.0, 0.0, 0.0, -half_c,
+                0.0, 0.0, -half_c, 0.0
+            ),
+            Surface::HyperbolicParaboloid => Matrix4::new(
+                a, 0.0, 0.0, 0.0,
+                0.0, -b, 0.0, 0.0,
+                0.0, 0.0, 0.0, -half_c,
+                0.0, 0.0, -half_c, 0.0
+            ),
+            Surface::EllipticCone => Matrix4::from_diagonal(&Vector4::new(a, b, -c2, 0.0)),
+            Surface::EllipticCylinder => Matrix4::from_diagonal(&Vector4::new(a, b, 0.0, -1.0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: f64 = 2.0;
+    const B: f64 = 3.0;
+    const C: f64 = 5.0;
+
+    type Point = (f64, f64, f64);
+
+    // Points on each surface for the sizes above, read off its equation, and one point off it.
+    fn points(surface: Surface) -> (Vec<Point>, Point) {
+        let root2 = 2.0f64.sqrt();
+        match surface {
+            Surface::Ellipsoid => (vec![(A, 0.0, 0.0), (0.0, -B, 0.0), (0.0, 0.0, C)], (0.0, 0.0, 0.0)),
+            Surface::HyperboloidOfOneSheet => (vec![(A, 0.0, 0.0), (0.0, B, 0.0), (root2 * A, 0.0, C)], (0.0, 0.0, 0.0)),
+            Surface::HyperboloidOfTwoSheets => (vec![(0.0, 0.0, C), (0.0, 0.0, -C), (A, 0.0, root2 * C)], (0.0, 0.0, 0.0)),
+            Surface::EllipticParaboloid => (vec![(0.0, 0.0, 0.0), (A, 0.0, C), (0.0, -B, C)], (0.0, 0.0, C)),
+            Surface::HyperbolicParaboloid => (vec![(0.0, 0.0, 0.0), (A, 0.0, C), (0.0, B, -C)], (0.0, 0.0, C)),
+            Surface::EllipticCone => (vec![(0.0, 0.0, 0.0), (A, 0.0, C), (0.0, B, -C)], (A, 0.0, 0.0)),
+            Surface::EllipticCylinder => (vec![(A, 0.0, 0.0), (0.0, -B, 0.0), (A, 0.0, 7.0)], (0.0, 0.0, 0.0)),
+        }
+    }
+
+    #[test]
+    fn forms_vanish_on_their_surfaces() {
+        for surface in Surface::ALL {
+            let d = surface.form(A, B, C);
+            assert_eq!(d, d.transpose(), "{:?}", surface);
+
+            let value = |(x, y, z): Point| {
+                let p = Vector4::new(x, y, z, 1.0);
+                p.dot(&(d * p))
+            };
+            let (on, off) = points(surface);
+            for point in on {
+                assert!(value(point).abs() < 1e-12, "{:?} at {:?} gives {}", surface, point, value(point));
+            }
+            assert!(value(off).abs() > 0.5, "{:?} at {:?} gives {}", surface, off, value(off));
+        }
+    }
+}
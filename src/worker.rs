@@ -0,0 +1,95 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use druid::{ExtEventSink, Selector, SingleUse, WidgetId};
+use raycasting::{Precision, Renderer, Scene};
+
+pub const FRAME_READY: Selector<SingleUse<Frame>> = Selector::new("raycasting.frame-ready");
+
+pub struct Frame {
+    pub buffer: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+    pub accuracy: usize,
+    pub generation: u64,
+    // False if the renderer had already drawn this frame. The buffer is then left empty and the
+    // canvas keeps the image it has.
+    pub changed: bool,
+    // How long the pass took and how many pixels it traced, to estimate the cost of the next one.
+    pub elapsed: Duration,
+    pub pixels_shaded: usize,
+}
+
+struct Job {
+    scene: Scene,
+    width: usize,
+    height: usize,
+    accuracy: usize,
+    threads: usize,
+    precision: Precision,
+    generation: u64,
+}
+
+pub struct RenderWorker {
+    jobs: Sender<Job>,
+    generation: Arc<AtomicU64>,
+}
+
+impl RenderWorker {
+    pub fn spawn(sink: ExtEventSink, target: WidgetId) -> Self {
+        let (jobs, receiver) = mpsc::channel();
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let worker_generation = generation.clone();
+        thread::Builder::new()
+            .name("renderer".to_string())
+            .spawn(move || run(receiver, worker_generation, sink, target))
+            .expect("Failed to spawn render thread");
+
+        RenderWorker { jobs, generation }
+    }
+
+    // Any job still in flight is cancelled as soon as a newer one is requested.
+    pub fn request(&self, scene: Scene, width: usize, height: usize, accuracy: usize, threads: usize, precision: Precision) -> u64 {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = self.jobs.send(Job { scene, width, height, accuracy, threads, precision, generation });
+        generation
+    }
+}
+
+fn run(jobs: Receiver<Job>, generation: Arc<AtomicU64>, sink: ExtEventSink, target: WidgetId) {
+    let mut renderer = Renderer::new();
+
+    while let Ok(mut job) = jobs.recv() {
+        while let Ok(newer) = jobs.try_recv() {
+            job = newer;
+        }
+
+        renderer.set_threads(job.threads);
+        renderer.set_precision(job.precision);
+        renderer.set_accuracy(job.accuracy);
+        let cancelled = || generation.load(Ordering::SeqCst) != job.generation;
+        let start = Instant::now();
+        if !renderer.draw_cancellable(&job.scene, job.width, job.height, &cancelled) {
+            continue;
+        }
+        let elapsed = start.elapsed();
+
+        let changed = renderer.redrawn();
+        let frame = Frame {
+            buffer: if changed { renderer.buffer().to_vec() } else { Vec::new() },
+            width: job.width,
+            height: job.height,
+            accuracy: job.accuracy,
+            generation: job.generation,
+            changed,
+            elapsed,
+            pixels_shaded: renderer.pixels_shaded(),
+        };
+        if sink.submit_command(FRAME_READY, SingleUse::new(frame), target).is_err() {
+            break;
+        }
+    }
+}